@@ -0,0 +1,37 @@
+use axum::http::StatusCode;
+use serde::Serialize;
+// imports
+use crate::middleware::AdminIdentity;
+use cirith_shared::storage::{DatabaseTx, NewAuditEntry};
+
+pub async fn record<T: Serialize>(
+    tx: &mut DatabaseTx,
+    identity: &AdminIdentity,
+    action: &str,
+    entity: &str,
+    entity_id: &str,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<(), StatusCode> {
+    let entry = NewAuditEntry {
+        actor: identity.name.clone(),
+        action: action.to_string(),
+        entity: entity.to_string(),
+        entity_id: entity_id.to_string(),
+        before: before.and_then(|value| serde_json::to_value(value).ok()),
+        after: after.and_then(|value| serde_json::to_value(value).ok()),
+        source_ip: identity.source_ip.map(|ip| ip.to_string()),
+    };
+
+    tx.add_audit_entry(&entry).await.map_err(|e| {
+        tracing::error!(
+            actor = %entry.actor,
+            action = %entry.action,
+            entity = %entry.entity,
+            entity_id = %entry.entity_id,
+            "Failed to write audit record: {}",
+            e
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
@@ -0,0 +1,65 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+// imports
+use crate::state::AdminState;
+use cirith_shared::storage::AuditFilter;
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub entity: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Normalizes a query timestamp to the UTC format SQLite stores in
+/// `created_at`, so an unparseable value is rejected instead of matching
+/// nothing.
+fn parse_timestamp(value: &str) -> Option<String> {
+    let timestamp = DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc).naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .ok()?;
+    Some(timestamp.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn parse_bound(value: Option<String>) -> Result<Option<String>, StatusCode> {
+    value
+        .map(|value| parse_timestamp(&value).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()
+}
+
+pub async fn list_audit_log(
+    State(state): State<Arc<AdminState>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let filter = AuditFilter {
+        entity: query.entity,
+        from: parse_bound(query.from)?,
+        to: parse_bound(query.to)?,
+        limit: query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+
+    let entries = state
+        .database
+        .get_audit_log(&filter)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(entries))
+}
@@ -0,0 +1,105 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+// imports
+use crate::state::AdminState;
+use cirith_shared::auth::hash::hash_password;
+use cirith_shared::storage::DbBasicAuthCredential;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBasicAuthRequest {
+    pub username: String,
+    pub password: String,
+    pub consumer: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BasicAuthResponse {
+    pub id: i64,
+    pub username: String,
+    pub consumer_id: Option<i64>,
+}
+
+impl From<DbBasicAuthCredential> for BasicAuthResponse {
+    fn from(credential: DbBasicAuthCredential) -> Self {
+        Self {
+            id: credential.id,
+            username: credential.username,
+            consumer_id: credential.consumer_id,
+        }
+    }
+}
+
+pub async fn list_basic_auth_credentials(
+    State(state): State<Arc<AdminState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let credentials = state
+        .database
+        .get_basic_auth_credentials()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let response: Vec<BasicAuthResponse> = credentials
+        .into_iter()
+        .map(BasicAuthResponse::from)
+        .collect();
+
+    Ok(Json(response))
+}
+
+pub async fn create_basic_auth_credential(
+    State(state): State<Arc<AdminState>>,
+    Json(payload): Json<CreateBasicAuthRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if payload.username.is_empty() || payload.username.contains(':') {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let consumer_id = match &payload.consumer {
+        Some(name) => {
+            let consumer = state
+                .database
+                .get_consumer(name)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            Some(consumer.id)
+        }
+        None => None,
+    };
+
+    let password_hash =
+        hash_password(&payload.password).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let credential = state
+        .database
+        .add_basic_auth_credential(&payload.username, &password_hash, consumer_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(BasicAuthResponse::from(credential)),
+    ))
+}
+
+pub async fn delete_basic_auth_credential(
+    State(state): State<Arc<AdminState>>,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let deleted = state
+        .database
+        .delete_basic_auth_credential(&username)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+// imports
+use crate::state::AdminState;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateClientCertRequest {
+    pub subject: String,
+    pub consumer: Option<String>,
+}
+
+pub async fn list_client_certs(
+    State(state): State<Arc<AdminState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let certificates = state
+        .database
+        .get_client_certificates()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(certificates))
+}
+
+pub async fn create_client_cert(
+    State(state): State<Arc<AdminState>>,
+    Json(payload): Json<CreateClientCertRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if payload.subject.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let consumer_id = match &payload.consumer {
+        Some(name) => {
+            let consumer = state
+                .database
+                .get_consumer(name)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            Some(consumer.id)
+        }
+        None => None,
+    };
+
+    let certificate = state
+        .database
+        .add_client_certificate(&payload.subject, consumer_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(certificate)))
+}
+
+pub async fn delete_client_cert(
+    State(state): State<Arc<AdminState>>,
+    Path(subject): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let deleted = state
+        .database
+        .delete_client_certificate(&subject)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
@@ -0,0 +1,111 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+// imports
+use crate::handlers::keys::ApiKeyResponse;
+use crate::state::AdminState;
+use cirith_shared::config::RateLimit;
+use cirith_shared::quota::Quota;
+use cirith_shared::storage::DbConsumer;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateConsumerRequest {
+    pub name: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub rate_limit: Option<RateLimit>,
+    pub quota: Option<Quota>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsumerDetailResponse {
+    #[serde(flatten)]
+    pub consumer: DbConsumer,
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+pub async fn list_consumers(
+    State(state): State<Arc<AdminState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let consumers = state
+        .database
+        .get_consumers()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(consumers))
+}
+
+pub async fn get_consumer(
+    State(state): State<Arc<AdminState>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let consumer = state
+        .database
+        .get_consumer(&name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let api_keys = state
+        .database
+        .get_consumer_api_keys(consumer.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(ApiKeyResponse::from)
+        .collect();
+
+    Ok(Json(ConsumerDetailResponse { consumer, api_keys }))
+}
+
+pub async fn create_consumer(
+    State(state): State<Arc<AdminState>>,
+    Json(payload): Json<CreateConsumerRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if payload.name.trim().is_empty()
+        || payload.rate_limit.is_some_and(|limit| !limit.is_valid())
+        || payload.quota.is_some_and(|quota| !quota.is_valid())
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let consumer = state
+        .database
+        .add_consumer(
+            &payload.name,
+            &payload.metadata,
+            &payload.tags,
+            payload.rate_limit.as_ref(),
+            payload.quota.as_ref(),
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(consumer)))
+}
+
+pub async fn delete_consumer(
+    State(state): State<Arc<AdminState>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let deleted = state
+        .database
+        .delete_consumer(&name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
@@ -0,0 +1,119 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+// imports
+use crate::state::AdminState;
+use cirith_shared::auth::secret::{SecretCipher, generate_secret};
+use cirith_shared::storage::DbHmacCredential;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHmacKeyRequest {
+    pub key_id: String,
+    pub secret: Option<String>,
+    pub consumer: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HmacKeyResponse {
+    pub id: i64,
+    pub key_id: String,
+    pub consumer_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl From<DbHmacCredential> for HmacKeyResponse {
+    fn from(credential: DbHmacCredential) -> Self {
+        Self {
+            id: credential.id,
+            key_id: credential.key_id,
+            consumer_id: credential.consumer_id,
+            secret: None,
+        }
+    }
+}
+
+pub async fn list_hmac_keys(
+    State(state): State<Arc<AdminState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let credentials = state
+        .database
+        .get_hmac_credentials()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let response: Vec<HmacKeyResponse> =
+        credentials.into_iter().map(HmacKeyResponse::from).collect();
+
+    Ok(Json(response))
+}
+
+pub async fn create_hmac_key(
+    State(state): State<Arc<AdminState>>,
+    Json(payload): Json<CreateHmacKeyRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if payload.key_id.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let Some(encryption_key) = &state.config.auth.encryption_key else {
+        tracing::error!("Cannot store HMAC secret, auth.encryption_key is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+    let cipher =
+        SecretCipher::new(encryption_key).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let consumer_id = match &payload.consumer {
+        Some(name) => {
+            let consumer = state
+                .database
+                .get_consumer(name)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            Some(consumer.id)
+        }
+        None => None,
+    };
+
+    let generated = payload.secret.is_none();
+    let secret = payload.secret.unwrap_or_else(generate_secret);
+    let secret_encrypted = cipher
+        .encrypt(&secret)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let credential = state
+        .database
+        .add_hmac_credential(&payload.key_id, &secret_encrypted, consumer_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut response = HmacKeyResponse::from(credential);
+    if generated {
+        response.secret = Some(secret);
+    }
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+pub async fn delete_hmac_key(
+    State(state): State<Arc<AdminState>>,
+    Path(key_id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let deleted = state
+        .database
+        .delete_hmac_credential(&key_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+// imports
+use crate::state::AdminState;
+use cirith_shared::ip_filter::{Cidr, IpAction};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateIpRuleRequest {
+    pub cidr: String,
+    pub action: IpAction,
+    pub route: Option<String>,
+}
+
+pub async fn list_ip_rules(
+    State(state): State<Arc<AdminState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let rules = state
+        .database
+        .get_ip_rules()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rules))
+}
+
+pub async fn create_ip_rule(
+    State(state): State<Arc<AdminState>>,
+    Json(payload): Json<CreateIpRuleRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let cidr: Cidr = payload.cidr.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let route_id = match &payload.route {
+        Some(path) => {
+            let route = state
+                .database
+                .get_route(path)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            Some(route.id)
+        }
+        None => None,
+    };
+
+    let rule = state
+        .database
+        .add_ip_rule(&cidr.to_string(), payload.action, route_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+pub async fn delete_ip_rule(
+    State(state): State<Arc<AdminState>>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let deleted = state
+        .database
+        .delete_ip_rule(id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
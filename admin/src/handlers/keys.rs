@@ -1,5 +1,5 @@
 use axum::{
-    Json,
+    Extension, Json,
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
@@ -7,19 +7,41 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 // imports
+use crate::audit;
+use crate::middleware::AdminIdentity;
 use crate::state::AdminState;
-use cirith_shared::auth::hash_key;
+use cirith_shared::config::RateLimit;
+use cirith_shared::quota::Quota;
+use cirith_shared::storage::DbApiKey;
 
 #[derive(Debug, Deserialize)]
 pub struct CreateApiKeyRequest {
     pub name: String,
     pub key: String,
+    pub consumer: Option<String>,
+    pub rate_limit: Option<RateLimit>,
+    pub quota: Option<Quota>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ApiKeyResponse {
     pub id: i64,
     pub name: String,
+    pub consumer_id: Option<i64>,
+    pub rate_limit: Option<RateLimit>,
+    pub quota: Option<Quota>,
+}
+
+impl From<DbApiKey> for ApiKeyResponse {
+    fn from(key: DbApiKey) -> Self {
+        Self {
+            id: key.id,
+            name: key.name,
+            consumer_id: key.consumer_id,
+            rate_limit: key.rate_limit.map(|limit| limit.0),
+            quota: key.quota.map(|quota| quota.0),
+        }
+    }
 }
 
 pub async fn list_api_keys(
@@ -31,48 +53,109 @@ pub async fn list_api_keys(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let response: Vec<ApiKeyResponse> = api_keys
-        .into_iter()
-        .map(|k| ApiKeyResponse {
-            id: k.id,
-            name: k.name,
-        })
-        .collect();
+    let response: Vec<ApiKeyResponse> = api_keys.into_iter().map(ApiKeyResponse::from).collect();
 
     Ok(Json(response))
 }
 
 pub async fn create_api_key(
     State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
     Json(payload): Json<CreateApiKeyRequest>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    let key_hash = hash_key(&payload.key);
-    let key = state
+    if payload.rate_limit.is_some_and(|limit| !limit.is_valid())
+        || payload.quota.is_some_and(|quota| !quota.is_valid())
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let consumer_id = match &payload.consumer {
+        Some(name) => {
+            let consumer = state
+                .database
+                .get_consumer(name)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            Some(consumer.id)
+        }
+        None => None,
+    };
+
+    let Some(key_hash) = state.auth_validator.hash_key(&payload.key) else {
+        tracing::error!("Cannot hash API key, auth.pepper is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+    let mut tx = state
         .database
-        .add_api_key(&payload.name, &key_hash)
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let key = tx
+        .add_api_key(
+            &payload.name,
+            &key_hash,
+            consumer_id,
+            payload.rate_limit.as_ref(),
+            payload.quota.as_ref(),
+        )
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok((
-        StatusCode::CREATED,
-        Json(ApiKeyResponse {
-            id: key.id,
-            name: key.name,
-        }),
-    ))
+    let key = ApiKeyResponse::from(key);
+    audit::record(
+        &mut tx,
+        &identity,
+        "create",
+        "api_key",
+        &key.name,
+        None,
+        Some(&key),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(key)))
 }
 
 pub async fn delete_api_key(
     State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
     Path(name): Path<String>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    let deleted = state
+    let before = state
         .database
+        .get_api_key(&name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(ApiKeyResponse::from);
+
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let deleted = tx
         .delete_api_key(&name)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     if deleted {
+        audit::record(
+            &mut tx,
+            &identity,
+            "delete",
+            "api_key",
+            &name,
+            before.as_ref(),
+            None,
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
         Ok(StatusCode::NO_CONTENT)
     } else {
         Err(StatusCode::NOT_FOUND)
@@ -1,3 +1,11 @@
+pub mod audit;
+pub mod basic_auth;
+pub mod client_certs;
+pub mod consumers;
 pub mod health;
+pub mod hmac_keys;
+pub mod ip_rules;
 pub mod keys;
+pub mod quotas;
+pub mod rate_limits;
 pub mod routes;
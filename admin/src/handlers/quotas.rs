@@ -0,0 +1,118 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use std::sync::Arc;
+// imports
+use crate::audit;
+use crate::middleware::AdminIdentity;
+use crate::state::AdminState;
+use cirith_shared::quota::{Quota, QuotaSubject, QuotaUsage};
+
+async fn quota_usage(
+    state: &AdminState,
+    subject: String,
+    quota: Quota,
+) -> Result<QuotaUsage, StatusCode> {
+    let usage = state
+        .database
+        .get_subject_quota_usage(&subject)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(QuotaUsage::new(
+        subject,
+        quota,
+        usage.as_ref(),
+        chrono::Utc::now(),
+    ))
+}
+
+async fn api_key_quota(state: &AdminState, name: &str) -> Result<QuotaUsage, StatusCode> {
+    let key = state
+        .database
+        .get_api_key(name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let quota = key.quota.ok_or(StatusCode::NOT_FOUND)?;
+
+    quota_usage(state, QuotaSubject::ApiKey(name).to_string(), quota.0).await
+}
+
+async fn consumer_quota(state: &AdminState, name: &str) -> Result<QuotaUsage, StatusCode> {
+    let consumer = state
+        .database
+        .get_consumer(name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let quota = consumer.quota.ok_or(StatusCode::NOT_FOUND)?;
+
+    quota_usage(state, QuotaSubject::Consumer(name).to_string(), quota.0).await
+}
+
+async fn reset(
+    state: &AdminState,
+    identity: &AdminIdentity,
+    usage: QuotaUsage,
+) -> Result<StatusCode, StatusCode> {
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.reset_quota_usage(&usage.subject)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &mut tx,
+        identity,
+        "reset",
+        "quota",
+        &usage.subject,
+        Some(&usage),
+        None,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_api_key_quota(
+    State(state): State<Arc<AdminState>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    Ok(Json(api_key_quota(&state, &name).await?))
+}
+
+pub async fn reset_api_key_quota(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usage = api_key_quota(&state, &name).await?;
+    reset(&state, &identity, usage).await
+}
+
+pub async fn get_consumer_quota(
+    State(state): State<Arc<AdminState>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    Ok(Json(consumer_quota(&state, &name).await?))
+}
+
+pub async fn reset_consumer_quota(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let usage = consumer_quota(&state, &name).await?;
+    reset(&state, &identity, usage).await
+}
@@ -0,0 +1,388 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Serialize;
+use std::sync::Arc;
+// imports
+use crate::audit;
+use crate::middleware::AdminIdentity;
+use crate::state::AdminState;
+use cirith_shared::config::{RateLimit, RateLimitPolicy};
+
+#[derive(Debug, Serialize)]
+pub struct GlobalRateLimit {
+    #[serde(flatten)]
+    pub policy: RateLimitPolicy,
+    pub source: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteRateLimit {
+    pub path: String,
+    pub rate_limit: RateLimitPolicy,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsumerRateLimit {
+    pub name: String,
+    pub rate_limit: RateLimit,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RateLimitsResponse {
+    pub global: GlobalRateLimit,
+    pub routes: Vec<RouteRateLimit>,
+    pub consumers: Vec<ConsumerRateLimit>,
+}
+
+async fn global_rate_limit(state: &AdminState) -> Result<GlobalRateLimit, StatusCode> {
+    let policy = state
+        .database
+        .get_global_rate_limit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(match policy {
+        Some(policy) => GlobalRateLimit {
+            policy,
+            source: "database",
+        },
+        None => GlobalRateLimit {
+            policy: state.config.rate_limit.policy(),
+            source: "config",
+        },
+    })
+}
+
+async fn route_rate_limit(
+    state: &AdminState,
+    path: &str,
+) -> Result<Option<RouteRateLimit>, StatusCode> {
+    let route = state
+        .database
+        .get_route(path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(route.rate_limit.map(|policy| RouteRateLimit {
+        path: route.path,
+        rate_limit: policy.0,
+    }))
+}
+
+async fn consumer_rate_limit(
+    state: &AdminState,
+    name: &str,
+) -> Result<Option<ConsumerRateLimit>, StatusCode> {
+    let consumer = state
+        .database
+        .get_consumer(name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(consumer.rate_limit.map(|limit| ConsumerRateLimit {
+        name: consumer.name,
+        rate_limit: limit.0,
+    }))
+}
+
+pub async fn list_rate_limits(
+    State(state): State<Arc<AdminState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let global = global_rate_limit(&state).await?;
+    let routes = state
+        .database
+        .get_routes()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let consumers = state
+        .database
+        .get_consumers()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(RateLimitsResponse {
+        global,
+        routes: routes
+            .into_iter()
+            .filter_map(|route| {
+                Some(RouteRateLimit {
+                    rate_limit: route.rate_limit?.0,
+                    path: route.path,
+                })
+            })
+            .collect(),
+        consumers: consumers
+            .into_iter()
+            .filter_map(|consumer| {
+                Some(ConsumerRateLimit {
+                    rate_limit: consumer.rate_limit?.0,
+                    name: consumer.name,
+                })
+            })
+            .collect(),
+    }))
+}
+
+pub async fn get_global_rate_limit(
+    State(state): State<Arc<AdminState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    Ok(Json(global_rate_limit(&state).await?))
+}
+
+pub async fn update_global_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Json(payload): Json<RateLimitPolicy>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let before = global_rate_limit(&state).await?;
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.set_global_rate_limit(&payload)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let after = GlobalRateLimit {
+        policy: payload,
+        source: "database",
+    };
+    audit::record(
+        &mut tx,
+        &identity,
+        "update",
+        "rate_limit",
+        "global",
+        Some(&before),
+        Some(&after),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(after))
+}
+
+pub async fn delete_global_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let before = global_rate_limit(&state).await?;
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let deleted = tx
+        .delete_global_rate_limit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if deleted {
+        audit::record(
+            &mut tx,
+            &identity,
+            "delete",
+            "rate_limit",
+            "global",
+            Some(&before),
+            None,
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub async fn get_route_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Path(path): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let path = format!("/{}", path);
+    let rate_limit = route_rate_limit(&state, &path)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(rate_limit))
+}
+
+pub async fn update_route_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(path): Path<String>,
+    Json(payload): Json<RateLimitPolicy>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let path = format!("/{}", path);
+    let before = route_rate_limit(&state, &path).await?;
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.update_route_rate_limit(&path, Some(&payload))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let after = RouteRateLimit {
+        path,
+        rate_limit: payload,
+    };
+    audit::record(
+        &mut tx,
+        &identity,
+        "update",
+        "rate_limit",
+        &format!("route:{}", after.path),
+        before.as_ref(),
+        Some(&after),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(after))
+}
+
+pub async fn delete_route_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(path): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let path = format!("/{}", path);
+    let before = route_rate_limit(&state, &path)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.update_route_rate_limit(&path, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &mut tx,
+        &identity,
+        "delete",
+        "rate_limit",
+        &format!("route:{}", path),
+        Some(&before),
+        None,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_consumer_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let rate_limit = consumer_rate_limit(&state, &name)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(rate_limit))
+}
+
+pub async fn update_consumer_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(name): Path<String>,
+    Json(payload): Json<RateLimit>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let before = consumer_rate_limit(&state, &name).await?;
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.update_consumer_rate_limit(&name, Some(&payload))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let after = ConsumerRateLimit {
+        name,
+        rate_limit: payload,
+    };
+    audit::record(
+        &mut tx,
+        &identity,
+        "update",
+        "rate_limit",
+        &format!("consumer:{}", after.name),
+        before.as_ref(),
+        Some(&after),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(after))
+}
+
+pub async fn delete_consumer_rate_limit(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let before = consumer_rate_limit(&state, &name)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.update_consumer_rate_limit(&name, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &mut tx,
+        &identity,
+        "delete",
+        "rate_limit",
+        &format!("consumer:{}", name),
+        Some(&before),
+        None,
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,19 +1,66 @@
 use axum::{
-    Json,
+    Extension, Json,
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::sync::Arc;
 // module imports
+use crate::audit;
+use crate::middleware::AdminIdentity;
 use crate::state::AdminState;
+use cirith_shared::auth::AuthMode;
+use cirith_shared::config::{ConcurrencyLimit, CorsPolicy, LoadShedding, RateLimitPolicy};
+use cirith_shared::storage::{NewRoute, RoutePolicies};
+use cirith_shared::transform::{HeaderTransforms, ResponseHeaderRules};
 use cirith_shared::validation::{validate_path, validate_upstream_url};
 
 #[derive(Debug, Deserialize)]
 pub struct CreateRouteRequest {
     pub path: String,
     pub upstream: String,
+    pub auth: Option<AuthMode>,
+    #[serde(default)]
+    pub forward_authorization: bool,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    pub rate_limit: Option<RateLimitPolicy>,
+    pub concurrency: Option<ConcurrencyLimit>,
+    pub load_shedding: Option<LoadShedding>,
+    pub request_headers: Option<HeaderTransforms>,
+    pub response_headers: Option<ResponseHeaderRules>,
+    pub cors: Option<CorsPolicy>,
+}
+
+/// Policies left out of the body are kept, `null` removes one.
+#[derive(Debug, Deserialize)]
+pub struct UpdateRouteRequest {
+    #[serde(default, deserialize_with = "present")]
+    pub rate_limit: Option<Option<RateLimitPolicy>>,
+    #[serde(default, deserialize_with = "present")]
+    pub concurrency: Option<Option<ConcurrencyLimit>>,
+    #[serde(default, deserialize_with = "present")]
+    pub load_shedding: Option<Option<LoadShedding>>,
+    #[serde(default, deserialize_with = "present")]
+    pub request_headers: Option<Option<HeaderTransforms>>,
+    #[serde(default, deserialize_with = "present")]
+    pub response_headers: Option<Option<ResponseHeaderRules>>,
+    #[serde(default, deserialize_with = "present")]
+    pub cors: Option<Option<CorsPolicy>>,
+}
+
+// Wraps a present field, including an explicit null, in `Some`.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+fn merge<T: Clone>(update: Option<Option<T>>, current: Option<&T>) -> Option<T> {
+    update.unwrap_or_else(|| current.cloned())
 }
 
 pub async fn list_routes(
@@ -28,8 +75,10 @@ pub async fn list_routes(
     Ok(Json(routes))
 }
 
+#[allow(clippy::redundant_pattern_matching)]
 pub async fn create_route(
     State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
     Json(payload): Json<CreateRouteRequest>,
 ) -> Result<impl IntoResponse, StatusCode> {
     if let Err(_) = validate_upstream_url(&payload.upstream) {
@@ -40,27 +89,142 @@ pub async fn create_route(
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let route = state
+    let policies = RoutePolicies {
+        rate_limit: payload.rate_limit,
+        concurrency: payload.concurrency,
+        load_shedding: payload.load_shedding,
+        request_headers: payload.request_headers,
+        response_headers: payload.response_headers,
+        cors: payload.cors,
+    };
+    if !policies.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = state
         .database
-        .add_route(&payload.path, &payload.upstream)
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let route = tx
+        .add_route(&NewRoute {
+            path: payload.path,
+            upstream: payload.upstream,
+            auth: payload.auth,
+            forward_authorization: payload.forward_authorization,
+            scopes: payload.scopes,
+            policies,
+        })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &mut tx,
+        &identity,
+        "create",
+        "route",
+        &route.path,
+        None,
+        Some(&route),
+    )
+    .await?;
+    tx.commit()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok((StatusCode::CREATED, Json(route)))
 }
 
+pub async fn update_route(
+    State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
+    Path(path): Path<String>,
+    Json(payload): Json<UpdateRouteRequest>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let path = format!("/{}", path);
+    let before = state
+        .database
+        .get_route(&path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let policies = RoutePolicies {
+        rate_limit: merge(payload.rate_limit, before.rate_limit.as_deref()),
+        concurrency: merge(payload.concurrency, before.concurrency.as_deref()),
+        load_shedding: merge(payload.load_shedding, before.load_shedding.as_deref()),
+        request_headers: merge(payload.request_headers, before.request_headers.as_deref()),
+        response_headers: merge(payload.response_headers, before.response_headers.as_deref()),
+        cors: merge(payload.cors, before.cors.as_deref()),
+    };
+    if !policies.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = state
+        .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let route = tx
+        .update_route_policies(&path, &policies)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    audit::record(
+        &mut tx,
+        &identity,
+        "update",
+        "route",
+        &path,
+        Some(&before),
+        Some(&route),
+    )
+    .await?;
+    tx.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(route))
+}
+
 pub async fn delete_route(
     State(state): State<Arc<AdminState>>,
+    Extension(identity): Extension<AdminIdentity>,
     Path(path): Path<String>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let path = format!("/{}", path);
-    let deleted = state
+    let before = state
+        .database
+        .get_route(&path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut tx = state
         .database
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let deleted = tx
         .delete_route(&path)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     if deleted {
+        audit::record(
+            &mut tx,
+            &identity,
+            "delete",
+            "route",
+            &path,
+            before.as_ref(),
+            None,
+        )
+        .await?;
+        tx.commit()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
         Ok(StatusCode::NO_CONTENT)
     } else {
         Err(StatusCode::NOT_FOUND)
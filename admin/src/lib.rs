@@ -1,15 +1,33 @@
 use axum::{
     Router,
     middleware::from_fn_with_state,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
 };
 use std::sync::Arc;
 // imports
+use crate::handlers::audit::list_audit_log;
+use crate::handlers::basic_auth::{
+    create_basic_auth_credential, delete_basic_auth_credential, list_basic_auth_credentials,
+};
+use crate::handlers::client_certs::{create_client_cert, delete_client_cert, list_client_certs};
+use crate::handlers::consumers::{create_consumer, delete_consumer, get_consumer, list_consumers};
 use crate::handlers::health::{health_check, metrics_handler};
+use crate::handlers::hmac_keys::{create_hmac_key, delete_hmac_key, list_hmac_keys};
+use crate::handlers::ip_rules::{create_ip_rule, delete_ip_rule, list_ip_rules};
 use crate::handlers::keys::{create_api_key, delete_api_key, list_api_keys};
-use crate::handlers::routes::{create_route, delete_route, list_routes};
+use crate::handlers::quotas::{
+    get_api_key_quota, get_consumer_quota, reset_api_key_quota, reset_consumer_quota,
+};
+use crate::handlers::rate_limits::{
+    delete_consumer_rate_limit, delete_global_rate_limit, delete_route_rate_limit,
+    get_consumer_rate_limit, get_global_rate_limit, get_route_rate_limit, list_rate_limits,
+    update_consumer_rate_limit, update_global_rate_limit, update_route_rate_limit,
+};
+use crate::handlers::routes::{create_route, delete_route, list_routes, update_route};
 use crate::state::AdminState;
+use cirith_shared::config::AdminRole;
 
+pub mod audit;
 pub mod handlers;
 pub mod metrics;
 pub mod middleware;
@@ -18,14 +36,83 @@ pub mod state;
 pub fn create_app(state: Arc<AdminState>) -> Router {
     let public_routes = Router::new().route("/health", get(health_check));
 
-    let protected_routes = Router::new()
+    let read_routes = Router::new()
         .route("/metrics", get(metrics_handler))
         .route("/admin/routes", get(list_routes))
-        .route("/admin/routes", post(create_route))
-        .route("/admin/routes/{*path}", delete(delete_route))
         .route("/admin/keys", get(list_api_keys))
+        .route("/admin/consumers", get(list_consumers))
+        .route("/admin/consumers/{name}", get(get_consumer))
+        .route("/admin/consumers/{name}/quota", get(get_consumer_quota))
+        .route("/admin/keys/{name}/quota", get(get_api_key_quota))
+        .route("/admin/rate-limits", get(list_rate_limits))
+        .route("/admin/rate-limits/global", get(get_global_rate_limit))
+        .route(
+            "/admin/rate-limits/routes/{*path}",
+            get(get_route_rate_limit),
+        )
+        .route(
+            "/admin/rate-limits/consumers/{name}",
+            get(get_consumer_rate_limit),
+        )
+        .route("/admin/basic-auth", get(list_basic_auth_credentials))
+        .route("/admin/hmac-keys", get(list_hmac_keys))
+        .route("/admin/client-certs", get(list_client_certs))
+        .route("/admin/ip-rules", get(list_ip_rules))
+        .route("/admin/audit", get(list_audit_log));
+
+    let routes_admin_routes = Router::new()
+        .route("/admin/routes", post(create_route))
+        .route(
+            "/admin/routes/{*path}",
+            put(update_route).delete(delete_route),
+        )
+        .route(
+            "/admin/rate-limits/global",
+            put(update_global_rate_limit).delete(delete_global_rate_limit),
+        )
+        .route(
+            "/admin/rate-limits/routes/{*path}",
+            put(update_route_rate_limit).delete(delete_route_rate_limit),
+        )
+        .route("/admin/ip-rules", post(create_ip_rule))
+        .route("/admin/ip-rules/{id}", delete(delete_ip_rule))
+        .route_layer(from_fn_with_state(
+            AdminRole::RoutesAdmin,
+            middleware::require_role,
+        ));
+
+    let keys_admin_routes = Router::new()
         .route("/admin/keys", post(create_api_key))
         .route("/admin/keys/{name}", delete(delete_api_key))
+        .route("/admin/consumers", post(create_consumer))
+        .route("/admin/consumers/{name}", delete(delete_consumer))
+        .route("/admin/keys/{name}/quota", delete(reset_api_key_quota))
+        .route(
+            "/admin/consumers/{name}/quota",
+            delete(reset_consumer_quota),
+        )
+        .route(
+            "/admin/rate-limits/consumers/{name}",
+            put(update_consumer_rate_limit).delete(delete_consumer_rate_limit),
+        )
+        .route("/admin/basic-auth", post(create_basic_auth_credential))
+        .route(
+            "/admin/basic-auth/{username}",
+            delete(delete_basic_auth_credential),
+        )
+        .route("/admin/hmac-keys", post(create_hmac_key))
+        .route("/admin/hmac-keys/{key_id}", delete(delete_hmac_key))
+        .route("/admin/client-certs", post(create_client_cert))
+        .route("/admin/client-certs/{subject}", delete(delete_client_cert))
+        .route_layer(from_fn_with_state(
+            AdminRole::KeysAdmin,
+            middleware::require_role,
+        ));
+
+    let protected_routes = Router::new()
+        .merge(read_routes)
+        .merge(routes_admin_routes)
+        .merge(keys_admin_routes)
         .layer(from_fn_with_state(
             state.clone(),
             middleware::auth_middleware,
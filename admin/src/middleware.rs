@@ -1,17 +1,27 @@
 use axum::{
     body::Body,
-    extract::State,
-    http::{Request, StatusCode},
+    extract::{ConnectInfo, State},
+    http::{Method, Request, StatusCode},
     middleware::Next,
     response::IntoResponse,
 };
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 // imports
 use crate::state::AdminState;
+use cirith_shared::auth::hash::{constant_time_eq, verify_secret};
+use cirith_shared::config::AdminRole;
+
+#[derive(Debug, Clone)]
+pub struct AdminIdentity {
+    pub name: String,
+    pub role: AdminRole,
+    pub source_ip: Option<IpAddr>,
+}
 
 pub async fn auth_middleware(
     State(state): State<Arc<AdminState>>,
-    request: Request<Body>,
+    mut request: Request<Body>,
     next: Next,
 ) -> Result<impl IntoResponse, StatusCode> {
     let auth_header = request.headers().get("authorization");
@@ -23,12 +33,84 @@ pub async fn auth_middleware(
                 .strip_prefix("Bearer ")
                 .ok_or(StatusCode::UNAUTHORIZED)?;
 
-            if token != state.config.admin.token {
-                return Err(StatusCode::UNAUTHORIZED);
-            }
+            // Admin token hashes may be argon2, so keep them off the executor.
+            let token = token.to_string();
+            let mut identity = tokio::task::spawn_blocking(move || authenticate(&state, &token))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::UNAUTHORIZED)?;
+            identity.source_ip = request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip());
+            request.extensions_mut().insert(identity);
 
             Ok(next.run(request).await)
         }
         None => Err(StatusCode::UNAUTHORIZED),
     }
 }
+
+pub async fn require_role(
+    State(required): State<AdminRole>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, StatusCode> {
+    let identity = request
+        .extensions()
+        .get::<AdminIdentity>()
+        .cloned()
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !identity.role.allows(required) {
+        tracing::warn!(token = %identity.name, role = ?identity.role, required = ?required, "Admin request forbidden");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    if method != Method::GET {
+        tracing::info!(
+            token = %identity.name,
+            method = %method,
+            path = %path,
+            status = response.status().as_u16(),
+            "Admin change"
+        );
+    }
+
+    Ok(response)
+}
+
+fn authenticate(state: &AdminState, token: &str) -> Option<AdminIdentity> {
+    if let Some(admin_token) = &state.config.admin.token
+        && constant_time_eq(token, admin_token)
+    {
+        return Some(AdminIdentity {
+            name: String::from("default"),
+            role: AdminRole::Superuser,
+            source_ip: None,
+        });
+    }
+
+    state
+        .config
+        .admin
+        .tokens
+        .iter()
+        .find(|admin_token| {
+            verify_secret(
+                token,
+                &admin_token.token_hash,
+                state.config.auth.pepper.as_deref(),
+            )
+            .is_valid()
+        })
+        .map(|admin_token| AdminIdentity {
+            name: admin_token.name.clone(),
+            role: admin_token.role,
+            source_ip: None,
+        })
+}
@@ -9,32 +9,58 @@ use cirith_admin::create_app;
 use cirith_admin::metrics::Metrics;
 use cirith_admin::state::AdminState;
 use cirith_shared::auth::AuthValidator;
+use cirith_shared::auth::hash::hash_api_key;
 use cirith_shared::config::{
-    AdminConfig, AuthConfig, Config, DatabaseConfig, RateLimitConfig, ServerConfig,
+    AdminConfig, AdminRole, AdminToken, AuthConfig, Config, DatabaseConfig, RateLimitConfig,
+    ServerConfig,
 };
 use cirith_shared::storage::Database;
 
 async fn setup_test_app() -> axum::Router {
+    setup_test_app_with_tokens(vec![]).await
+}
+
+async fn setup_test_app_with_tokens(tokens: Vec<AdminToken>) -> axum::Router {
     let database = Database::new(":memory:").await.unwrap();
     let config = Config {
         server: ServerConfig {
             admin_port: 3000,
             gateway_port: 6191,
             timeout_seconds: 30,
+            tls: None,
+            trusted_proxies: vec![],
+            forwarded_header: Default::default(),
+            proxy_protocol: false,
+            metrics_port: None,
         },
         auth: AuthConfig {
             enabled: false,
+            pepper: Some("test-pepper".to_string()),
             api_keys: vec![],
+            encryption_key: Some("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=".to_string()),
+            signature: Default::default(),
+            introspection: None,
+            identity_headers: Default::default(),
         },
         rate_limit: RateLimitConfig {
             max_requests: 100,
             window_secs: 60,
+            key_by: Default::default(),
+            max_keys: 100_000,
+            redis: None,
+            quota_flush_secs: 10,
+            reload_secs: 5,
+            shadow: false,
+            exempt: Default::default(),
         },
+        concurrency: Default::default(),
+        response_headers: Default::default(),
         database: DatabaseConfig {
             url: ":memory:".to_string(),
         },
         admin: AdminConfig {
-            token: "test-token".to_string(),
+            token: Some("test-token".to_string()),
+            tokens,
         },
     };
 
@@ -161,3 +187,1196 @@ async fn test_create_route_invalid_path_returns_400() {
 
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
+
+#[tokio::test]
+async fn test_create_consumer_returns_201() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/consumers")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"name": "partner", "metadata": {"plan": "gold"}, "tags": ["external"]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_create_api_key_for_unknown_consumer_returns_400() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/keys")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"name": "partner-key", "key": "secret", "consumer": "missing"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_get_consumer_lists_owned_api_keys() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/consumers")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"name": "partner"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/keys")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"name": "partner-key", "key": "secret", "consumer": "partner"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/consumers/partner")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let consumer: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(consumer["name"], "partner");
+    assert_eq!(consumer["api_keys"][0]["name"], "partner-key");
+}
+
+#[tokio::test]
+async fn test_api_key_rate_limit_override() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/keys")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"name": "batch-key", "key": "secret", "rate_limit": {"max_requests": 1000, "window_secs": 60}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let key: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(key["rate_limit"]["max_requests"], 1000);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/consumers")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"name": "partner", "rate_limit": {"max_requests": 0, "window_secs": 60}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_consumer_quota_usage_and_reset() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/consumers")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"name": "partner", "quota": {"limit": 100000, "period": "monthly"}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/consumers/partner/quota")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let usage: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(usage["subject"], "consumer:partner");
+    assert_eq!(usage["limit"], 100000);
+    assert_eq!(usage["used"], 0);
+    assert_eq!(usage["period"], "monthly");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/admin/consumers/partner/quota")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/keys/missing/quota")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_basic_auth_credential_returns_201() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/basic-auth")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"username": "legacy", "password": "hunter2"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let credential: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(credential["username"], "legacy");
+    assert!(credential.get("password_hash").is_none());
+}
+
+#[tokio::test]
+async fn test_delete_consumer_removes_credentials() {
+    let app = setup_test_app().await;
+    for (uri, body) in [
+        ("/admin/consumers", r#"{"name": "partner"}"#),
+        (
+            "/admin/basic-auth",
+            r#"{"username": "legacy", "password": "hunter2", "consumer": "partner"}"#,
+        ),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header("Authorization", "Bearer test-token")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/admin/consumers/partner")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/basic-auth")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let credentials: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(credentials, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_create_route_with_basic_auth_returns_201() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/legacy", "upstream": "https://httpbin.org", "auth": "basic"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["auth"], "basic");
+    assert_eq!(route["forward_authorization"], false);
+}
+
+#[tokio::test]
+async fn test_create_route_with_oauth2_scopes_returns_201() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/orders", "upstream": "https://httpbin.org", "auth": "oauth2", "scopes": ["orders:read"]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["auth"], "oauth2");
+    assert_eq!(route["scopes"][0], "orders:read");
+}
+
+#[tokio::test]
+async fn test_update_route_policies() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/search", "upstream": "https://httpbin.org"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/search")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"rate_limit": {"max_requests": 10, "window_secs": 60, "key_by": "consumer"}, "concurrency": {"max_in_flight": 5, "queue_size": 2}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["rate_limit"]["max_requests"], 10);
+    assert_eq!(route["rate_limit"]["key_by"], "consumer");
+    assert_eq!(route["concurrency"]["max_in_flight"], 5);
+    assert_eq!(route["concurrency"]["queue_timeout_ms"], 1000);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/search")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"rate_limit": {"max_requests": 10, "window_secs": 0}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/search")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"concurrency": {"max_in_flight": 0}}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/search")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"load_shedding": {"min_limit": 10, "max_limit": 5}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/search")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"load_shedding": {"max_limit": 50, "premium_tags": ["premium"]}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["load_shedding"]["min_limit"], 1);
+    assert_eq!(route["load_shedding"]["latency_tolerance"], 2.0);
+    assert_eq!(route["rate_limit"]["max_requests"], 10);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/missing")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"rate_limit": null}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_update_route_keeps_unrelated_policies() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/search", "upstream": "https://httpbin.org", "rate_limit": {"max_requests": 10, "window_secs": 60}, "cors": {"allowed_origins": ["https://app.example.com"]}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let update = |body: &'static str| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/search")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    };
+
+    let response = update(r#"{"concurrency": {"max_in_flight": 5}}"#)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["concurrency"]["max_in_flight"], 5);
+    assert_eq!(route["rate_limit"]["max_requests"], 10);
+    assert_eq!(
+        route["cors"]["allowed_origins"][0],
+        "https://app.example.com"
+    );
+
+    let response = update(r#"{"rate_limit": null}"#).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(route["rate_limit"].is_null());
+    assert_eq!(route["concurrency"]["max_in_flight"], 5);
+    assert_eq!(
+        route["cors"]["allowed_origins"][0],
+        "https://app.example.com"
+    );
+}
+
+#[tokio::test]
+async fn test_route_request_headers() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/users", "upstream": "https://httpbin.org", "request_headers": {"set": {"X-User-Id": "${path.1}"}, "unknown": 1}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["request_headers"]["set"]["X-User-Id"], "${path.1}");
+    assert_eq!(route["request_headers"]["remove"], serde_json::json!([]));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/users")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"request_headers": {"add": {"X-Forwarded-By": "${consumer.nickname}"}}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/users")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"request_headers": {"remove": ["Cookie"], "rename": {"X-Api-Version": "Accept-Version"}, "add": {"X-Request-Id": "${request_id}"}}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["request_headers"]["remove"][0], "Cookie");
+    assert_eq!(
+        route["request_headers"]["rename"]["X-Api-Version"],
+        "Accept-Version"
+    );
+    assert!(
+        route["request_headers"]["set"]
+            .as_object()
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_route_response_headers() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/web", "upstream": "https://httpbin.org", "response_headers": {"security_headers": true, "remove": ["Server", "X-Powered-By"]}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["response_headers"]["security_headers"], true);
+    assert_eq!(route["response_headers"]["remove"][1], "X-Powered-By");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/routes/web")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"response_headers": {"set": {"X-Served By": "cirith"}}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_route_cors_policy() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/api", "upstream": "https://httpbin.org", "cors": {"allowed_origins": ["*"], "allow_credentials": true}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/api", "upstream": "https://httpbin.org", "cors": {"allowed_origins": ["https://*.example.com"], "allow_credentials": true, "max_age_secs": 600}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let route: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(route["cors"]["allowed_origins"][0], "https://*.example.com");
+    assert_eq!(route["cors"]["allowed_methods"][0], "GET");
+    assert_eq!(route["cors"]["max_age_secs"], 600);
+}
+
+#[tokio::test]
+async fn test_global_rate_limit_override() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/rate-limits/global")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let global: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(global["max_requests"], 100);
+    assert_eq!(global["source"], "config");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/rate-limits/global")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"max_requests": 500, "window_secs": 60, "exempt": {"cidrs": ["10.0.0.0/33"]}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/admin/rate-limits/global")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"max_requests": 500, "window_secs": 60, "key_by": "api-key", "shadow": true, "exempt": {"api_keys": ["monitoring"], "cidrs": ["10.1.0.0/16"]}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/rate-limits")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let rate_limits: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(rate_limits["global"]["max_requests"], 500);
+    assert_eq!(rate_limits["global"]["key_by"], "api-key");
+    assert_eq!(rate_limits["global"]["source"], "database");
+    assert_eq!(rate_limits["global"]["shadow"], true);
+    assert_eq!(rate_limits["global"]["exempt"]["cidrs"][0], "10.1.0.0/16");
+    assert_eq!(rate_limits["routes"].as_array().unwrap().len(), 0);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/admin/rate-limits/global")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/admin/rate-limits/global")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_consumer_rate_limit_policy() {
+    let app = setup_test_app_with_tokens(vec![admin_token("ops", AdminRole::RoutesAdmin)]).await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/consumers")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"name": "partner"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let put = |token: &str, body: &'static str| {
+        Request::builder()
+            .method("PUT")
+            .uri("/admin/rate-limits/consumers/partner")
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(put(
+            "ops-secret",
+            r#"{"max_requests": 10, "window_secs": 60}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .clone()
+        .oneshot(put(
+            "test-token",
+            r#"{"max_requests": 0, "window_secs": 60}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(put(
+            "test-token",
+            r#"{"max_requests": 10, "window_secs": 60}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/rate-limits/consumers/partner")
+                .header("Authorization", "Bearer ops-secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let rate_limit: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(rate_limit["name"], "partner");
+    assert_eq!(rate_limit["rate_limit"]["max_requests"], 10);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/admin/rate-limits/consumers/partner")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/rate-limits/consumers/partner")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_hmac_key_returns_generated_secret() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/hmac-keys")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"key_id": "partner"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let credential: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(credential["key_id"], "partner");
+    assert_eq!(credential["secret"].as_str().unwrap().len(), 64);
+    assert!(credential.get("secret_encrypted").is_none());
+}
+
+#[tokio::test]
+async fn test_create_client_cert_returns_201() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/client-certs")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"subject": "partner.example.com"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let certificate: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(certificate["subject"], "partner.example.com");
+}
+
+#[tokio::test]
+async fn test_create_ip_rule_normalizes_cidr() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/ip-rules")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"cidr": "10.1.2.3/8", "action": "deny"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let rule: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(rule["cidr"], "10.0.0.0/8");
+    assert_eq!(rule["action"], "deny");
+    assert!(rule["route_id"].is_null());
+}
+
+#[tokio::test]
+async fn test_create_ip_rule_invalid_cidr_returns_400() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/ip-rules")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"cidr": "10.0.0.0/40", "action": "allow"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+fn admin_token(name: &str, role: AdminRole) -> AdminToken {
+    AdminToken {
+        name: name.to_string(),
+        token_hash: hash_api_key(&format!("{}-secret", name), "test-pepper"),
+        role,
+    }
+}
+
+#[tokio::test]
+async fn test_read_only_token_cannot_create_route() {
+    let app = setup_test_app_with_tokens(vec![admin_token("auditor", AdminRole::ReadOnly)]).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer auditor-secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer auditor-secret")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/api", "upstream": "https://httpbin.org"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_keys_admin_token_is_scoped_to_credentials() {
+    let app = setup_test_app_with_tokens(vec![admin_token("issuer", AdminRole::KeysAdmin)]).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/consumers")
+                .header("Authorization", "Bearer issuer-secret")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"name": "partner"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer issuer-secret")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/api", "upstream": "https://httpbin.org"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_unknown_admin_token_returns_401() {
+    let app = setup_test_app_with_tokens(vec![admin_token("auditor", AdminRole::ReadOnly)]).await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer wrong-secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_route_changes_are_audited() {
+    let app = setup_test_app().await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/admin/routes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"path": "/audited", "upstream": "https://httpbin.org"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/admin/routes/audited")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/audit?entity=route&from=2000-01-01T00:00:00")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let entries: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+    assert_eq!(entries[0]["action"], "delete");
+    assert_eq!(entries[0]["actor"], "default");
+    assert_eq!(entries[0]["before"]["upstream"], "https://httpbin.org");
+    assert!(entries[0]["after"].is_null());
+    assert_eq!(entries[1]["action"], "create");
+    assert_eq!(entries[1]["after"]["path"], "/audited");
+}
+
+#[tokio::test]
+async fn test_audit_log_rejects_invalid_time_filter() {
+    let app = setup_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/audit?from=yesterday")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,167 @@
+use pingora::http::RequestHeader;
+use std::net::IpAddr;
+// imports
+use cirith_shared::config::ForwardedHeader;
+use cirith_shared::ip_filter::CidrTrie;
+
+/// Only `header` is read; the other forwarding header is client-controlled.
+pub fn resolve_client_ip(
+    peer: IpAddr,
+    trusted_proxies: &CidrTrie,
+    header: ForwardedHeader,
+    request: &RequestHeader,
+) -> IpAddr {
+    if !trusted_proxies.contains(peer) {
+        return peer;
+    }
+
+    let values: Vec<&str> = request
+        .headers
+        .get_all(header.name())
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+
+    let hops: Vec<Option<IpAddr>> = match header {
+        ForwardedHeader::XForwardedFor => values
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(parse_node)
+            .collect(),
+        ForwardedHeader::Forwarded => values
+            .iter()
+            .flat_map(|value| value.split(','))
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, value)| parse_node(value))
+            })
+            .collect(),
+    };
+
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        match hop {
+            Some(ip) => {
+                client = ip;
+                if !trusted_proxies.contains(ip) {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    client
+}
+
+fn parse_node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    if let Ok(ip) = value.parse() {
+        return Some(ip);
+    }
+
+    if let Some(rest) = value.strip_prefix('[') {
+        let (ip, _) = rest.split_once(']')?;
+        return ip.parse().ok();
+    }
+
+    let (ip, port) = value.rsplit_once(':')?;
+    port.parse::<u16>().ok()?;
+    ip.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn request(headers: &[(&str, &str)]) -> RequestHeader {
+        let mut request = RequestHeader::build("GET", b"/", None).unwrap();
+        for (name, value) in headers {
+            request.append_header(name.to_string(), *value).unwrap();
+        }
+        request
+    }
+
+    fn trusted() -> CidrTrie {
+        ["10.0.0.0/8".parse().unwrap()].into_iter().collect()
+    }
+
+    #[test]
+    fn test_untrusted_peer_is_the_client() {
+        let client = resolve_client_ip(
+            ip("203.0.113.9"),
+            &trusted(),
+            ForwardedHeader::XForwardedFor,
+            &request(&[("X-Forwarded-For", "198.51.100.1")]),
+        );
+        assert_eq!(client, ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn test_x_forwarded_for_skips_trusted_hops() {
+        let client = resolve_client_ip(
+            ip("10.0.0.1"),
+            &trusted(),
+            ForwardedHeader::XForwardedFor,
+            &request(&[
+                ("X-Forwarded-For", "1.1.1.1, 198.51.100.7"),
+                ("X-Forwarded-For", "10.0.0.2"),
+            ]),
+        );
+        assert_eq!(client, ip("198.51.100.7"));
+    }
+
+    #[test]
+    fn test_forwarded_header() {
+        let client = resolve_client_ip(
+            ip("10.0.0.1"),
+            &trusted(),
+            ForwardedHeader::Forwarded,
+            &request(&[
+                (
+                    "Forwarded",
+                    r#"for="[2001:db8::1]:4711";proto=https, for=10.0.0.3"#,
+                ),
+                ("X-Forwarded-For", "198.51.100.7"),
+            ]),
+        );
+        assert_eq!(client, ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn test_spoofed_forwarded_header_is_ignored() {
+        let client = resolve_client_ip(
+            ip("10.0.0.1"),
+            &trusted(),
+            ForwardedHeader::XForwardedFor,
+            &request(&[
+                ("Forwarded", "for=192.0.2.1"),
+                ("X-Forwarded-For", "203.0.113.50"),
+            ]),
+        );
+        assert_eq!(client, ip("203.0.113.50"));
+    }
+
+    #[test]
+    fn test_unparsable_hop_stops_at_last_trusted_address() {
+        let client = resolve_client_ip(
+            ip("10.0.0.1"),
+            &trusted(),
+            ForwardedHeader::Forwarded,
+            &request(&[("Forwarded", "for=unknown, for=10.0.0.5")]),
+        );
+        assert_eq!(client, ip("10.0.0.5"));
+    }
+
+    #[test]
+    fn test_parse_node_with_port() {
+        assert_eq!(parse_node("192.0.2.60:8080"), Some(ip("192.0.2.60")));
+        assert_eq!(parse_node("_hidden"), None);
+    }
+}
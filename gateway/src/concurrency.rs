@@ -0,0 +1,333 @@
+use prometheus::{
+    IntCounterVec, IntGauge, IntGaugeVec, register_int_counter_vec, register_int_gauge_vec,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+// imports
+use cirith_shared::config::ConcurrencyLimit;
+
+static IN_FLIGHT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "cirith_in_flight_requests",
+        "Requests currently holding a concurrency slot",
+        &["scope", "name"]
+    )
+    .unwrap()
+});
+
+static QUEUED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "cirith_queued_requests",
+        "Requests waiting for a concurrency slot",
+        &["scope", "name"]
+    )
+    .unwrap()
+});
+
+static REJECTED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "cirith_concurrency_rejected_total",
+        "Requests rejected because a concurrency limit was saturated",
+        &["scope", "name"]
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConcurrencyScope {
+    Route,
+    Upstream,
+}
+
+impl ConcurrencyScope {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Route => "route",
+            Self::Upstream => "upstream",
+        }
+    }
+}
+
+pub struct ConcurrencyPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    limiter: Arc<Limiter>,
+    in_flight: IntGauge,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        if let Some(permit) = self.permit.take() {
+            self.limiter.release(permit);
+        }
+        self.in_flight.dec();
+    }
+}
+
+struct QueueSlot<'a> {
+    queued: &'a AtomicUsize,
+    gauge: IntGauge,
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        self.gauge.dec();
+    }
+}
+
+struct Limiter {
+    limit: Mutex<ConcurrencyLimit>,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    // Permits held by in-flight requests that are forgotten on release
+    // because the limit was lowered while they were running.
+    excess: AtomicUsize,
+}
+
+impl Limiter {
+    fn new(limit: ConcurrencyLimit) -> Self {
+        Self {
+            limit: Mutex::new(limit),
+            semaphore: Arc::new(Semaphore::new(limit.max_in_flight)),
+            queued: AtomicUsize::new(0),
+            excess: AtomicUsize::new(0),
+        }
+    }
+
+    fn resize(&self, limit: ConcurrencyLimit) {
+        let Ok(mut current) = self.limit.lock() else {
+            return;
+        };
+
+        let (old, new) = (current.max_in_flight, limit.max_in_flight);
+        if new > old {
+            let added = new - old;
+            let repaid = self
+                .excess
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |excess| {
+                    Some(excess.saturating_sub(added))
+                })
+                .unwrap_or_default()
+                .min(added);
+            self.semaphore.add_permits(added - repaid);
+        } else if new < old {
+            let removed = old - new;
+            let forgotten = self.semaphore.forget_permits(removed);
+            self.excess.fetch_add(removed - forgotten, Ordering::AcqRel);
+        }
+        *current = limit;
+    }
+
+    fn release(&self, permit: OwnedSemaphorePermit) {
+        let owed = self
+            .excess
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |excess| {
+                excess.checked_sub(1)
+            })
+            .is_ok();
+        if owed {
+            permit.forget();
+        }
+    }
+
+    fn in_flight(&self) -> usize {
+        let max_in_flight = self
+            .limit
+            .lock()
+            .map(|limit| limit.max_in_flight)
+            .unwrap_or_default();
+        (max_in_flight + self.excess.load(Ordering::Acquire))
+            .saturating_sub(self.semaphore.available_permits())
+    }
+
+    async fn acquire(&self, labels: &[&str]) -> Option<OwnedSemaphorePermit> {
+        let limit = *self.limit.lock().ok()?;
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+
+        if self.queued.fetch_add(1, Ordering::Relaxed) >= limit.queue_size {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            return None;
+        }
+        let slot = QueueSlot {
+            queued: &self.queued,
+            gauge: QUEUED.with_label_values(labels),
+        };
+        slot.gauge.inc();
+
+        let timeout = Duration::from_millis(limit.queue_timeout_ms);
+        let permit = tokio::time::timeout(timeout, self.semaphore.clone().acquire_owned()).await;
+        drop(slot);
+        permit.ok()?.ok()
+    }
+}
+
+pub struct ConcurrencyLimiter {
+    limiters: Mutex<HashMap<(ConcurrencyScope, String), Arc<Limiter>>>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new() -> Self {
+        Self {
+            limiters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn limiter(
+        &self,
+        scope: ConcurrencyScope,
+        name: &str,
+        limit: ConcurrencyLimit,
+    ) -> Option<Arc<Limiter>> {
+        let mut limiters = self.limiters.lock().ok()?;
+        let limiter = limiters
+            .entry((scope, name.to_string()))
+            .or_insert_with(|| Arc::new(Limiter::new(limit)));
+        limiter.resize(limit);
+        Some(limiter.clone())
+    }
+
+    pub async fn acquire(
+        &self,
+        scope: ConcurrencyScope,
+        name: &str,
+        limit: ConcurrencyLimit,
+    ) -> Option<ConcurrencyPermit> {
+        let labels = [scope.as_str(), name];
+        let limiter = self.limiter(scope, name, limit);
+        let permit = match &limiter {
+            Some(limiter) => limiter.acquire(&labels).await,
+            None => None,
+        };
+        let (Some(limiter), Some(permit)) = (limiter, permit) else {
+            REJECTED.with_label_values(&labels).inc();
+            return None;
+        };
+
+        let in_flight = IN_FLIGHT.with_label_values(&labels);
+        in_flight.inc();
+        Some(ConcurrencyPermit {
+            permit: Some(permit),
+            limiter,
+            in_flight,
+        })
+    }
+
+    pub fn in_flight(&self, scope: ConcurrencyScope, name: &str) -> usize {
+        let Ok(limiters) = self.limiters.lock() else {
+            return 0;
+        };
+        limiters
+            .get(&(scope, name.to_string()))
+            .map(|limiter| limiter.in_flight())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(max_in_flight: usize, queue_size: usize) -> ConcurrencyLimit {
+        ConcurrencyLimit {
+            max_in_flight,
+            queue_size,
+            queue_timeout_ms: 50,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_saturated_limit_rejects() {
+        let limiter = ConcurrencyLimiter::new();
+        let first = limiter
+            .acquire(ConcurrencyScope::Route, "/saturated", limit(1, 0))
+            .await;
+        assert!(first.is_some());
+        assert_eq!(limiter.in_flight(ConcurrencyScope::Route, "/saturated"), 1);
+
+        assert!(
+            limiter
+                .acquire(ConcurrencyScope::Route, "/saturated", limit(1, 0))
+                .await
+                .is_none()
+        );
+        assert!(
+            limiter
+                .acquire(ConcurrencyScope::Upstream, "/saturated", limit(1, 0))
+                .await
+                .is_some()
+        );
+
+        drop(first);
+        assert_eq!(limiter.in_flight(ConcurrencyScope::Route, "/saturated"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_gets_released_slot() {
+        let limiter = Arc::new(ConcurrencyLimiter::new());
+        let first = limiter
+            .acquire(ConcurrencyScope::Upstream, "queued", limit(1, 1))
+            .await
+            .unwrap();
+
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move {
+                limiter
+                    .acquire(ConcurrencyScope::Upstream, "queued", limit(1, 1))
+                    .await
+                    .is_some()
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(first);
+
+        assert!(waiting.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_queue_times_out() {
+        let limiter = ConcurrencyLimiter::new();
+        let _first = limiter
+            .acquire(ConcurrencyScope::Upstream, "timeout", limit(1, 1))
+            .await
+            .unwrap();
+
+        assert!(
+            limiter
+                .acquire(ConcurrencyScope::Upstream, "timeout", limit(1, 1))
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resizing_keeps_in_flight_requests_counted() {
+        let limiter = ConcurrencyLimiter::new();
+        let acquire = |max_in_flight| {
+            limiter.acquire(ConcurrencyScope::Route, "/resized", limit(max_in_flight, 0))
+        };
+
+        let mut permits = Vec::new();
+        for _ in 0..4 {
+            permits.push(acquire(4).await.unwrap());
+        }
+        assert!(acquire(2).await.is_none());
+        assert_eq!(limiter.in_flight(ConcurrencyScope::Route, "/resized"), 4);
+
+        permits.truncate(2);
+        assert!(acquire(2).await.is_none());
+        assert_eq!(limiter.in_flight(ConcurrencyScope::Route, "/resized"), 2);
+
+        permits.pop();
+        permits.push(acquire(2).await.unwrap());
+        assert!(acquire(3).await.is_some());
+        permits.push(acquire(3).await.unwrap());
+        assert!(acquire(3).await.is_none());
+        assert_eq!(limiter.in_flight(ConcurrencyScope::Route, "/resized"), 3);
+    }
+}
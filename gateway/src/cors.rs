@@ -0,0 +1,216 @@
+use pingora::Result;
+use pingora::http::{Method, RequestHeader, ResponseHeader};
+// imports
+use cirith_shared::config::CorsPolicy;
+
+const CORS_HEADERS: &[&str] = &[
+    "Access-Control-Allow-Origin",
+    "Access-Control-Allow-Credentials",
+    "Access-Control-Allow-Methods",
+    "Access-Control-Allow-Headers",
+    "Access-Control-Expose-Headers",
+    "Access-Control-Max-Age",
+];
+
+const PREFLIGHT_VARY: &str =
+    "Origin, Access-Control-Request-Method, Access-Control-Request-Headers";
+
+fn header<'a>(request: &'a RequestHeader, name: &str) -> Option<&'a str> {
+    request.headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+pub fn is_preflight(request: &RequestHeader) -> bool {
+    request.method == Method::OPTIONS
+        && request.headers.contains_key("origin")
+        && request
+            .headers
+            .contains_key("access-control-request-method")
+}
+
+fn insert_origin(response: &mut ResponseHeader, policy: &CorsPolicy, origin: &str) -> Result<()> {
+    if policy.allows_any_origin() {
+        response.insert_header("Access-Control-Allow-Origin", "*")?;
+    } else {
+        response.insert_header("Access-Control-Allow-Origin", origin)?;
+    }
+    if policy.allow_credentials {
+        response.insert_header("Access-Control-Allow-Credentials", "true")?;
+    }
+    Ok(())
+}
+
+pub fn preflight(policy: &CorsPolicy, request: &RequestHeader) -> Result<Option<ResponseHeader>> {
+    let origin = header(request, "origin").unwrap_or_default();
+    let method = header(request, "access-control-request-method").unwrap_or_default();
+    if !policy.allows_origin(origin) || !policy.allows_method(method) {
+        return Ok(None);
+    }
+
+    let mut response = ResponseHeader::build(204, None)?;
+    insert_origin(&mut response, policy, origin)?;
+    response.insert_header(
+        "Access-Control-Allow-Methods",
+        policy.allowed_methods.join(", "),
+    )?;
+    if !policy.allowed_headers.is_empty() {
+        response.insert_header(
+            "Access-Control-Allow-Headers",
+            policy.allowed_headers.join(", "),
+        )?;
+    } else if let Some(requested) = header(request, "access-control-request-headers") {
+        response.insert_header("Access-Control-Allow-Headers", requested)?;
+    }
+    if let Some(max_age) = policy.max_age_secs {
+        response.insert_header("Access-Control-Max-Age", max_age.to_string())?;
+    }
+    response.insert_header("Vary", PREFLIGHT_VARY)?;
+    response.insert_header("Content-Length", "0")?;
+    Ok(Some(response))
+}
+
+pub fn decorate(
+    policy: &CorsPolicy,
+    request: &RequestHeader,
+    response: &mut ResponseHeader,
+) -> Result<()> {
+    for name in CORS_HEADERS {
+        response.remove_header(*name);
+    }
+    if !policy.allows_any_origin() {
+        response.append_header("Vary", "Origin")?;
+    }
+
+    let Some(origin) = header(request, "origin") else {
+        return Ok(());
+    };
+    if !policy.allows_origin(origin) {
+        return Ok(());
+    }
+
+    insert_origin(response, policy, origin)?;
+    if !policy.exposed_headers.is_empty() {
+        response.insert_header(
+            "Access-Control-Expose-Headers",
+            policy.exposed_headers.join(", "),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(origins: &[&str]) -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allowed_methods: vec![String::from("GET"), String::from("POST")],
+            allowed_headers: Vec::new(),
+            exposed_headers: vec![String::from("X-Request-Id")],
+            allow_credentials: true,
+            max_age_secs: Some(600),
+        }
+    }
+
+    fn request(method: &str, headers: &[(&str, &str)]) -> RequestHeader {
+        let mut request = RequestHeader::build(method, b"/api", None).unwrap();
+        for (name, value) in headers {
+            request.insert_header(name.to_string(), *value).unwrap();
+        }
+        request
+    }
+
+    #[test]
+    fn test_origin_wildcards() {
+        let policy = policy(&["https://app.example.com", "https://*.example.org"]);
+        assert!(policy.allows_origin("https://app.example.com"));
+        assert!(policy.allows_origin("https://eu.admin.example.org"));
+        assert!(!policy.allows_origin("https://example.org"));
+        assert!(!policy.allows_origin("https://evil.com/.example.org"));
+        assert!(!policy.allows_origin("http://app.example.com"));
+        assert!(policy.allows_origin("https://App.Example.com"));
+        assert!(policy.allows_origin("HTTPS://EU.Example.ORG"));
+        assert!(policy.is_valid());
+
+        assert!(!self::policy(&["*"]).is_valid());
+        assert!(self::policy(&["*"]).allows_origin("https://anything.test"));
+    }
+
+    #[test]
+    fn test_preflight() {
+        let policy = policy(&["https://*.example.com"]);
+        let allowed = request(
+            "OPTIONS",
+            &[
+                ("Origin", "https://app.example.com"),
+                ("Access-Control-Request-Method", "POST"),
+                ("Access-Control-Request-Headers", "content-type, x-tenant"),
+            ],
+        );
+        assert!(is_preflight(&allowed));
+
+        let response = preflight(&policy, &allowed).unwrap().unwrap();
+        assert_eq!(response.status.as_u16(), 204);
+        assert_eq!(
+            response.headers["Access-Control-Allow-Origin"],
+            "https://app.example.com"
+        );
+        assert_eq!(response.headers["Access-Control-Allow-Credentials"], "true");
+        assert_eq!(
+            response.headers["Access-Control-Allow-Methods"],
+            "GET, POST"
+        );
+        assert_eq!(
+            response.headers["Access-Control-Allow-Headers"],
+            "content-type, x-tenant"
+        );
+        assert_eq!(response.headers["Access-Control-Max-Age"], "600");
+
+        let denied = request(
+            "OPTIONS",
+            &[
+                ("Origin", "https://app.example.com"),
+                ("Access-Control-Request-Method", "DELETE"),
+            ],
+        );
+        assert!(preflight(&policy, &denied).unwrap().is_none());
+        assert!(!is_preflight(&request("OPTIONS", &[])));
+    }
+
+    #[test]
+    fn test_decorate_replaces_upstream_headers() {
+        let policy = policy(&["https://app.example.com"]);
+        let mut response = ResponseHeader::build(200, None).unwrap();
+        response
+            .insert_header("Access-Control-Allow-Origin", "*")
+            .unwrap();
+
+        decorate(
+            &policy,
+            &request("GET", &[("Origin", "https://app.example.com")]),
+            &mut response,
+        )
+        .unwrap();
+        assert_eq!(
+            response.headers["Access-Control-Allow-Origin"],
+            "https://app.example.com"
+        );
+        assert_eq!(
+            response.headers["Access-Control-Expose-Headers"],
+            "X-Request-Id"
+        );
+        assert_eq!(response.headers["Vary"], "Origin");
+
+        let mut response = ResponseHeader::build(200, None).unwrap();
+        response
+            .insert_header("Access-Control-Allow-Origin", "*")
+            .unwrap();
+        decorate(
+            &policy,
+            &request("GET", &[("Origin", "https://evil.test")]),
+            &mut response,
+        )
+        .unwrap();
+        assert!(!response.headers.contains_key("Access-Control-Allow-Origin"));
+    }
+}
@@ -0,0 +1,316 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+// imports
+use cirith_shared::config::IntrospectionConfig;
+
+const MAX_CACHED_TOKENS: usize = 10_000;
+// Clock skew tolerated between the gateway and the authorization server.
+const EXP_LEEWAY_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TokenInfo {
+    pub active: bool,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub sub: Option<String>,
+    #[serde(default)]
+    pub exp: Option<u64>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl TokenInfo {
+    pub fn scopes(&self) -> impl Iterator<Item = &str> {
+        self.scope.as_deref().unwrap_or_default().split_whitespace()
+    }
+
+    pub fn has_scopes(&self, required: &[String]) -> bool {
+        required
+            .iter()
+            .all(|scope| self.scopes().any(|granted| granted == scope))
+    }
+
+    pub fn claims(&self) -> HashMap<String, String> {
+        let mut claims: HashMap<String, String> = self
+            .extra
+            .iter()
+            .filter_map(|(name, value)| match value {
+                serde_json::Value::String(s) => Some((name.clone(), s.clone())),
+                serde_json::Value::Number(n) => Some((name.clone(), n.to_string())),
+                serde_json::Value::Bool(b) => Some((name.clone(), b.to_string())),
+                _ => None,
+            })
+            .collect();
+
+        let known = [
+            ("scope", self.scope.clone()),
+            ("client_id", self.client_id.clone()),
+            ("username", self.username.clone()),
+            ("sub", self.sub.clone()),
+            ("exp", self.exp.map(|exp| exp.to_string())),
+        ];
+        for (name, value) in known {
+            if let Some(value) = value {
+                claims.insert(name.to_string(), value);
+            }
+        }
+        claims
+    }
+
+    pub fn subject(&self) -> Option<&str> {
+        self.sub
+            .as_deref()
+            .or(self.username.as_deref())
+            .or(self.client_id.as_deref())
+    }
+}
+
+struct CachedToken {
+    info: TokenInfo,
+    expires_at: Instant,
+}
+
+pub struct Introspector {
+    client: reqwest::Client,
+    config: IntrospectionConfig,
+    cache: Mutex<HashMap<[u8; 32], CachedToken>>,
+}
+
+impl Introspector {
+    pub fn new(config: IntrospectionConfig) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+
+        Ok(Self {
+            client,
+            config,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub async fn introspect(&self, token: &str) -> Result<TokenInfo, reqwest::Error> {
+        if let Some(info) = self.cached(token) {
+            return Ok(info);
+        }
+
+        let mut info: TokenInfo = self
+            .client
+            .post(&self.config.url)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[("token", token), ("token_type_hint", "access_token")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Some servers keep reporting a token as active after its `exp`.
+        if info.active
+            && info
+                .exp
+                .is_some_and(|exp| exp.saturating_add(EXP_LEEWAY_SECS) < unix_now())
+        {
+            info.active = false;
+        }
+
+        self.store(token, &info);
+        Ok(info)
+    }
+
+    fn cached(&self, token: &str) -> Option<TokenInfo> {
+        let cache = self.cache.lock().ok()?;
+        let entry = cache.get(&cache_key(token))?;
+        (entry.expires_at > Instant::now()).then(|| entry.info.clone())
+    }
+
+    fn store(&self, token: &str, info: &TokenInfo) {
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+
+        let now = Instant::now();
+        if cache.len() >= MAX_CACHED_TOKENS {
+            cache.retain(|_, entry| entry.expires_at > now);
+            if cache.len() >= MAX_CACHED_TOKENS {
+                return;
+            }
+        }
+
+        let mut ttl = Duration::from_secs(self.config.cache_ttl_secs);
+        if info.active
+            && let Some(exp) = info.exp
+        {
+            ttl = ttl.min(Duration::from_secs(exp.saturating_sub(unix_now())));
+        }
+
+        cache.insert(
+            cache_key(token),
+            CachedToken {
+                info: info.clone(),
+                expires_at: now + ttl,
+            },
+        );
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn cache_key(token: &str) -> [u8; 32] {
+    Sha256::digest(token).into()
+}
+
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let token = token.trim();
+    (!token.is_empty()).then_some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Form, Json, Router, extract::State, routing::post};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn introspect(
+        State(calls): State<Arc<AtomicUsize>>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Json<serde_json::Value> {
+        calls.fetch_add(1, Ordering::SeqCst);
+        match form.get("token").map(String::as_str) {
+            Some("good-token") => Json(serde_json::json!({
+                "active": true,
+                "scope": "orders:read orders:write",
+                "client_id": "partner",
+                "email": "ops@partner.example.com",
+            })),
+            Some("expired-token") => Json(serde_json::json!({
+                "active": true,
+                "client_id": "partner",
+                "exp": unix_now() - 120,
+            })),
+            Some("skewed-token") => Json(serde_json::json!({
+                "active": true,
+                "client_id": "partner",
+                "exp": unix_now() - 5,
+            })),
+            Some("slow-token") => {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Json(serde_json::json!({ "active": true }))
+            }
+            _ => Json(serde_json::json!({ "active": false })),
+        }
+    }
+
+    async fn mock_server() -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route("/introspect", post(introspect))
+            .with_state(calls.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/introspect", addr), calls)
+    }
+
+    fn introspector(url: String) -> Introspector {
+        Introspector::new(IntrospectionConfig {
+            url,
+            client_id: "cirith".to_string(),
+            client_secret: "secret".to_string(),
+            cache_ttl_secs: 60,
+            connect_timeout_ms: 1000,
+            timeout_ms: 200,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_active_token_is_cached() {
+        let (url, calls) = mock_server().await;
+        let introspector = introspector(url);
+
+        let info = introspector.introspect("good-token").await.unwrap();
+        assert!(info.active);
+        assert_eq!(info.subject(), Some("partner"));
+        assert!(info.has_scopes(&["orders:read".to_string()]));
+        assert!(!info.has_scopes(&["orders:delete".to_string()]));
+        assert_eq!(info.claims()["email"], "ops@partner.example.com");
+        assert_eq!(info.claims()["client_id"], "partner");
+
+        introspector.introspect("good-token").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_inactive_token_is_cached() {
+        let (url, calls) = mock_server().await;
+        let introspector = introspector(url);
+
+        assert!(!introspector.introspect("revoked").await.unwrap().active);
+        assert!(!introspector.introspect("revoked").await.unwrap().active);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_inactive() {
+        let (url, _) = mock_server().await;
+        let introspector = introspector(url);
+
+        assert!(
+            !introspector
+                .introspect("expired-token")
+                .await
+                .unwrap()
+                .active
+        );
+        assert!(
+            introspector
+                .introspect("skewed-token")
+                .await
+                .unwrap()
+                .active
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_endpoint_is_an_error() {
+        let introspector = introspector("http://127.0.0.1:1/introspect".to_string());
+        assert!(introspector.introspect("good-token").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_endpoint_times_out() {
+        let (url, _) = mock_server().await;
+        let introspector = introspector(url);
+
+        let error = introspector.introspect("slow-token").await.unwrap_err();
+        assert!(error.is_timeout());
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert_eq!(bearer_token("Bearer "), None);
+    }
+}
@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use pingora::server::ShutdownWatch;
+use pingora::services::background::BackgroundService;
+use std::sync::Arc;
+use std::time::Duration;
+// imports
+use cirith_shared::auth::AuthValidator;
+use cirith_shared::storage::Database;
+
+/// Writes API key hashes migrated by the validator back to the database.
+pub struct KeyMigrationFlush {
+    validator: AuthValidator,
+    database: Arc<Database>,
+    interval: Duration,
+}
+
+impl KeyMigrationFlush {
+    pub fn new(validator: AuthValidator, database: Arc<Database>, interval: Duration) -> Self {
+        Self {
+            validator,
+            database,
+            interval,
+        }
+    }
+
+    async fn flush(&self) -> Result<(), sqlx::Error> {
+        let mut pending = self.validator.take_migrations().into_iter();
+        while let Some(migration) = pending.next() {
+            match self
+                .database
+                .update_api_key_hash(&migration.name, &migration.legacy_hash, &migration.key_hash)
+                .await
+            {
+                Ok(true) => {
+                    tracing::info!(name = %migration.name, "Persisted migrated API key hash")
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    self.validator
+                        .requeue_migrations(std::iter::once(migration).chain(pending).collect());
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BackgroundService for KeyMigrationFlush {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    if let Err(e) = self.flush().await {
+                        tracing::error!("Failed to persist migrated API key hashes on shutdown: {}", e);
+                    }
+                    return;
+                }
+                _ = interval.tick() => {
+                    if let Err(e) = self.flush().await {
+                        tracing::warn!("Failed to persist migrated API key hashes: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cirith_shared::auth::hash::hash_api_key;
+    use cirith_shared::config::AuthConfig;
+
+    #[tokio::test]
+    async fn test_flush_persists_migrated_hash() {
+        let path = std::env::temp_dir().join(format!("cirith-keys-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let database = Database::new(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        let legacy_hash = "20eb0536650933acad4ae017f3df774426ea2e4187d13728c99a6baf5e37b2b3";
+        database
+            .add_api_key("partner", legacy_hash, None, None, None)
+            .await
+            .unwrap();
+
+        let config = AuthConfig {
+            enabled: true,
+            pepper: Some(String::from("pepper")),
+            api_keys: Vec::new(),
+            encryption_key: None,
+            signature: Default::default(),
+            introspection: None,
+            identity_headers: Default::default(),
+        };
+        let validator = AuthValidator::new(&config).with_database_keys(
+            database.get_consumers().await.unwrap(),
+            database.get_api_keys().await.unwrap(),
+        );
+        assert!(validator.validate("my-test-key").is_some());
+
+        let flush = KeyMigrationFlush::new(validator, Arc::new(database), Duration::from_secs(10));
+        flush.flush().await.unwrap();
+
+        let stored = flush
+            .database
+            .get_api_key("partner")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.key_hash, hash_api_key("my-test-key", "pepper"));
+        assert!(flush.validator.take_migrations().is_empty());
+    }
+}
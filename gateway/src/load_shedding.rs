@@ -0,0 +1,388 @@
+use prometheus::{
+    IntCounterVec, IntGauge, IntGaugeVec, register_int_counter_vec, register_int_gauge_vec,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+// imports
+use cirith_shared::auth::Identity;
+use cirith_shared::config::LoadShedding;
+
+const BACKOFF: f64 = 0.9;
+const BASELINE_WEIGHT: f64 = 0.05;
+const SLOW_BASELINE_WEIGHT: f64 = 0.005;
+
+static LIMIT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "cirith_adaptive_concurrency_limit",
+        "Current adaptive concurrency limit",
+        &["route"]
+    )
+    .unwrap()
+});
+
+static SHED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "cirith_shed_requests_total",
+        "Requests rejected by adaptive load shedding",
+        &["route", "priority"]
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub fn of(identity: Option<&Identity>, premium_tags: &[String]) -> Self {
+        let Some(identity) = identity else {
+            return Self::Low;
+        };
+        let premium = identity
+            .consumer
+            .as_ref()
+            .is_some_and(|consumer| consumer.tags.iter().any(|tag| premium_tags.contains(tag)));
+        if premium { Self::High } else { Self::Normal }
+    }
+
+    fn share(self) -> f64 {
+        match self {
+            Self::Low => 0.5,
+            Self::Normal => 0.8,
+            Self::High => 1.0,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+}
+
+struct Window {
+    config: LoadShedding,
+    limit: f64,
+    baseline: Option<f64>,
+    last_decrease: Option<Instant>,
+}
+
+struct Controller {
+    in_flight: AtomicUsize,
+    window: Mutex<Window>,
+    gauge: IntGauge,
+}
+
+impl Controller {
+    fn new(config: LoadShedding, gauge: IntGauge) -> Self {
+        gauge.set(config.max_limit as i64);
+        Self {
+            window: Mutex::new(Window {
+                limit: config.max_limit as f64,
+                config,
+                baseline: None,
+                last_decrease: None,
+            }),
+            in_flight: AtomicUsize::new(0),
+            gauge,
+        }
+    }
+
+    fn limit(&self) -> f64 {
+        self.window
+            .lock()
+            .map(|window| window.limit)
+            .unwrap_or_default()
+    }
+
+    // Keeps the in-flight count and adaptive state, so requests admitted
+    // under the old config still count against the new one.
+    fn reconfigure(&self, config: &LoadShedding) {
+        let Ok(mut window) = self.window.lock() else {
+            return;
+        };
+        if window.config == *config {
+            return;
+        }
+
+        window.limit = window
+            .limit
+            .clamp(config.min_limit as f64, config.max_limit as f64);
+        window.config = config.clone();
+        self.gauge.set(window.limit as i64);
+    }
+
+    fn try_acquire(self: &Arc<Self>, priority: Priority) -> Option<ShedPermit> {
+        let allowed = (self.limit() * priority.share()).ceil() as usize;
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < allowed).then_some(n + 1)
+            })
+            .ok()?;
+
+        Some(ShedPermit {
+            controller: self.clone(),
+            started: None,
+        })
+    }
+
+    fn record(&self, latency: Duration, overloaded: bool, now: Instant) {
+        let Ok(mut window) = self.window.lock() else {
+            return;
+        };
+
+        let sample = latency.as_secs_f64();
+        let baseline = *window.baseline.get_or_insert(sample);
+        let slow = overloaded || sample > baseline * window.config.latency_tolerance;
+
+        if slow {
+            let cooldown = Duration::from_millis(window.config.cooldown_ms);
+            let cooled_down = window
+                .last_decrease
+                .is_none_or(|at| now.saturating_duration_since(at) >= cooldown);
+            if cooled_down {
+                window.limit = (window.limit * BACKOFF).max(window.config.min_limit as f64);
+                window.last_decrease = Some(now);
+            }
+            window.baseline = Some(baseline + (sample - baseline) * SLOW_BASELINE_WEIGHT);
+        } else {
+            if self.in_flight.load(Ordering::Acquire) as f64 >= window.limit / 2.0 {
+                window.limit =
+                    (window.limit + 1.0 / window.limit).min(window.config.max_limit as f64);
+            }
+            window.baseline = Some(baseline + (sample - baseline) * BASELINE_WEIGHT);
+        }
+
+        self.gauge.set(window.limit as i64);
+    }
+}
+
+pub struct ShedPermit {
+    controller: Arc<Controller>,
+    started: Option<Instant>,
+}
+
+impl ShedPermit {
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    pub fn finish(&mut self, overloaded: bool) {
+        if let Some(started) = self.started.take() {
+            let now = Instant::now();
+            self.controller.record(now - started, overloaded, now);
+        }
+    }
+}
+
+impl Drop for ShedPermit {
+    fn drop(&mut self) {
+        self.controller.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+pub struct LoadShedder {
+    controllers: Mutex<HashMap<String, Arc<Controller>>>,
+}
+
+impl LoadShedder {
+    pub fn new() -> Self {
+        Self {
+            controllers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn controller(&self, route: &str, config: &LoadShedding) -> Option<Arc<Controller>> {
+        let mut controllers = self.controllers.lock().ok()?;
+        let controller = controllers.entry(route.to_string()).or_insert_with(|| {
+            Arc::new(Controller::new(
+                config.clone(),
+                LIMIT.with_label_values(&[route]),
+            ))
+        });
+        controller.reconfigure(config);
+        Some(controller.clone())
+    }
+
+    pub fn try_acquire(
+        &self,
+        route: &str,
+        config: &LoadShedding,
+        priority: Priority,
+    ) -> Option<ShedPermit> {
+        let permit = self
+            .controller(route, config)
+            .and_then(|controller| controller.try_acquire(priority));
+        if permit.is_none() {
+            SHED.with_label_values(&[route, priority.as_str()]).inc();
+        }
+        permit
+    }
+
+    pub fn limit(&self, route: &str) -> Option<usize> {
+        let controllers = self.controllers.lock().ok()?;
+        Some(controllers.get(route)?.limit() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cirith_shared::storage::DbConsumer;
+    use sqlx::types::Json;
+
+    fn config(max_limit: usize) -> LoadShedding {
+        LoadShedding {
+            min_limit: 1,
+            max_limit,
+            latency_tolerance: 2.0,
+            cooldown_ms: 100,
+            premium_tags: vec![String::from("premium")],
+        }
+    }
+
+    fn record(permit: &ShedPermit, latency: Duration, overloaded: bool, now: Instant) {
+        permit.controller.record(latency, overloaded, now);
+    }
+
+    #[test]
+    fn test_priority_from_consumer_tags() {
+        let config = config(10);
+        let identity = |tags: Vec<&str>| Identity {
+            consumer: Some(DbConsumer {
+                id: 1,
+                name: String::from("partner"),
+                metadata: Json(HashMap::new()),
+                tags: Json(tags.into_iter().map(String::from).collect()),
+                rate_limit: None,
+                quota: None,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(Priority::of(None, &config.premium_tags), Priority::Low);
+        assert_eq!(
+            Priority::of(Some(&identity(vec!["beta"])), &config.premium_tags),
+            Priority::Normal
+        );
+        assert_eq!(
+            Priority::of(Some(&identity(vec!["premium"])), &config.premium_tags),
+            Priority::High
+        );
+    }
+
+    #[test]
+    fn test_low_priority_is_shed_first() {
+        let shedder = LoadShedder::new();
+        let config = config(5);
+        let mut permits = Vec::new();
+
+        for _ in 0..3 {
+            permits.push(
+                shedder
+                    .try_acquire("/orders", &config, Priority::Low)
+                    .unwrap(),
+            );
+        }
+        assert!(
+            shedder
+                .try_acquire("/orders", &config, Priority::Low)
+                .is_none()
+        );
+
+        permits.push(
+            shedder
+                .try_acquire("/orders", &config, Priority::Normal)
+                .unwrap(),
+        );
+        assert!(
+            shedder
+                .try_acquire("/orders", &config, Priority::Normal)
+                .is_none()
+        );
+        assert!(
+            shedder
+                .try_acquire("/orders", &config, Priority::High)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_slow_upstream_reduces_limit() {
+        let shedder = LoadShedder::new();
+        let config = config(10);
+        let permit = shedder
+            .try_acquire("/slow", &config, Priority::High)
+            .unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        record(&permit, Duration::from_millis(10), false, at(0));
+        assert_eq!(shedder.limit("/slow"), Some(10));
+
+        record(&permit, Duration::from_millis(100), false, at(0));
+        assert_eq!(shedder.limit("/slow"), Some(9));
+
+        record(&permit, Duration::ZERO, true, at(99));
+        assert_eq!(shedder.limit("/slow"), Some(9));
+
+        for i in 1..=30 {
+            record(&permit, Duration::from_millis(10), true, at(i * 100));
+        }
+        assert_eq!(shedder.limit("/slow"), Some(1));
+    }
+
+    #[test]
+    fn test_limit_recovers_when_latency_returns_to_baseline() {
+        let shedder = LoadShedder::new();
+        let mut config = config(3);
+        config.min_limit = 2;
+        let permit = shedder
+            .try_acquire("/recover", &config, Priority::High)
+            .unwrap();
+
+        let now = Instant::now();
+        record(&permit, Duration::from_millis(10), false, now);
+        record(&permit, Duration::ZERO, true, now);
+        assert_eq!(shedder.limit("/recover"), Some(2));
+
+        let _second = shedder.try_acquire("/recover", &config, Priority::High);
+        for _ in 0..3 {
+            record(&permit, Duration::from_millis(10), false, now);
+        }
+        assert_eq!(shedder.limit("/recover"), Some(3));
+    }
+
+    #[test]
+    fn test_reconfiguring_keeps_in_flight_requests_counted() {
+        let shedder = LoadShedder::new();
+        let mut permits: Vec<ShedPermit> = (0..4)
+            .map(|_| {
+                shedder
+                    .try_acquire("/resized", &config(4), Priority::High)
+                    .unwrap()
+            })
+            .collect();
+
+        assert!(
+            shedder
+                .try_acquire("/resized", &config(2), Priority::High)
+                .is_none()
+        );
+        assert_eq!(shedder.limit("/resized"), Some(2));
+
+        permits.truncate(1);
+        assert!(
+            shedder
+                .try_acquire("/resized", &config(2), Priority::High)
+                .is_some()
+        );
+    }
+}
@@ -1,29 +1,485 @@
+mod client_ip;
+mod concurrency;
+mod cors;
+mod introspection;
+mod key_migration;
+mod load_shedding;
+mod mtls;
+mod proxy_protocol;
+mod quota;
 mod rate_limit;
+mod transform;
 
 use async_trait::async_trait;
-use pingora::Result;
+use bytes::{Bytes, BytesMut};
 use pingora::http::{RequestHeader, ResponseHeader};
 use pingora::server::Server;
+use pingora::services::background::background_service;
+use pingora::services::listening::Service;
 use pingora::upstreams::peer::HttpPeer;
-use pingora_proxy::{ProxyHttp, Session};
+use pingora::{ErrorSource, ErrorType, Result};
+use pingora_proxy::{FailToProxy, ProxyHttp, Session};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 // imports
-use crate::rate_limit::RateLimiter;
+use crate::client_ip::resolve_client_ip;
+use crate::concurrency::{ConcurrencyLimiter, ConcurrencyPermit, ConcurrencyScope};
+use crate::introspection::{Introspector, bearer_token};
+use crate::key_migration::KeyMigrationFlush;
+use crate::load_shedding::{LoadShedder, Priority, ShedPermit};
+use crate::mtls::{ClientCertRegistry, ClientCertificate};
+use crate::proxy_protocol::{PreboundListeners, ProxiedPeers, ProxyProtocolRelay};
+use crate::quota::{QuotaDecision, QuotaFlush, QuotaTracker};
+use crate::rate_limit::memory::{MemoryStore, RateLimitEviction};
+use crate::rate_limit::policies::{PolicyReload, PolicyStore, RateLimitPolicies};
+use crate::rate_limit::redis::RedisStore;
+use crate::rate_limit::{RateLimitDecision, RateLimiter, SHADOW_REJECTIONS};
+use crate::transform::{apply_security_headers, is_valid_request_id};
+use cirith_shared::auth::identity::IdentityField;
+use cirith_shared::auth::secret::SecretCipher;
+use cirith_shared::auth::signature::{CONTENT_SHA256_HEADER, ContentDigest, SIGNATURE_HEADERS};
+use cirith_shared::auth::{AuthMode, AuthValidator, Identity};
+use cirith_shared::config::{
+    ConcurrencyLimit, Config, FailureMode, RateLimit, RateLimitKey, RateLimitPolicy,
+};
+use cirith_shared::ip_filter::{CidrTrie, IpRules};
+use cirith_shared::quota::{Quota, QuotaSubject};
 use cirith_shared::storage::{Database, DbRoute};
-use cirith_shared::{auth::AuthValidator, config::Config};
+use cirith_shared::transform::{Variable, generate_request_id, path_segment};
+
+const CLIENT_CERT_SUBJECT_HEADER: &str = "X-Client-Cert-Subject";
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+const KEY_MIGRATION_FLUSH_SECS: u64 = 10;
+
+fn credential_headers(mode: AuthMode) -> &'static [&'static str] {
+    match mode {
+        AuthMode::None | AuthMode::Mtls => &[],
+        AuthMode::ApiKey => &["x-api-key"],
+        AuthMode::Basic | AuthMode::OAuth2 => &["authorization"],
+        AuthMode::Hmac => SIGNATURE_HEADERS,
+    }
+}
+
+fn insert_rate_limit_headers(
+    header: &mut ResponseHeader,
+    decision: &RateLimitDecision,
+) -> Result<()> {
+    header.insert_header("RateLimit-Limit", decision.limit.to_string())?;
+    header.insert_header("RateLimit-Remaining", decision.remaining.to_string())?;
+    header.insert_header("RateLimit-Reset", decision.reset_secs.to_string())?;
+    if let Some(retry_after) = decision.retry_after_secs {
+        header.insert_header("Retry-After", retry_after.to_string())?;
+    }
+    Ok(())
+}
+
+fn insert_quota_headers(header: &mut ResponseHeader, decision: &QuotaDecision) -> Result<()> {
+    header.insert_header("X-Quota-Limit", decision.limit.to_string())?;
+    header.insert_header("X-Quota-Remaining", decision.remaining.to_string())?;
+    header.insert_header("X-Quota-Reset", decision.reset_secs.to_string())?;
+    Ok(())
+}
+
+struct RateLimitCheck {
+    key: String,
+    limit: RateLimit,
+    policy: String,
+    shadow: bool,
+}
+
+enum AuthError {
+    Unauthorized(&'static str),
+    Forbidden(&'static str),
+    Unavailable(&'static str),
+}
+
+impl From<&'static str> for AuthError {
+    fn from(reason: &'static str) -> Self {
+        Self::Unauthorized(reason)
+    }
+}
+
+#[derive(Default)]
+struct RequestContext {
+    host: String,
+    route: Option<DbRoute>,
+    auth_mode: AuthMode,
+    identity: Option<Identity>,
+    client_ip: Option<IpAddr>,
+    client_cert: Option<ClientCertificate>,
+    rate_limit: Option<RateLimitDecision>,
+    quota: Option<QuotaDecision>,
+    concurrency: Vec<ConcurrencyPermit>,
+    load_shedding: Option<ShedPermit>,
+    signed_body: Option<SignedBody>,
+    error: Option<&'static str>,
+    request_id: String,
+}
+
+struct SignedBody {
+    digest: ContentDigest,
+    expected: String,
+    buffer: BytesMut,
+}
+
+impl RequestContext {
+    fn variable(&self, variable: Variable, request: &RequestHeader) -> Option<String> {
+        let route = self.route.as_ref();
+        match variable {
+            Variable::ClientIp => self.client_ip.map(|ip| ip.to_string()),
+            Variable::RequestId => Some(self.request_id.clone()),
+            Variable::Method => Some(request.method.to_string()),
+            Variable::Path => Some(request.uri.path().to_string()),
+            Variable::Route => route.map(|r| r.path.clone()),
+            Variable::PathSegment(index) => {
+                path_segment(request.uri.path(), &route?.path, index).map(str::to_string)
+            }
+            Variable::Header(name) => request
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            Variable::Identity(field) => field.resolve(self.identity.as_ref()?, self.auth_mode),
+        }
+    }
+}
 
 struct CirithGateway {
     config: Config,
     rate_limit: RateLimiter,
+    rate_limit_policies: Arc<PolicyStore>,
+    quotas: Arc<QuotaTracker>,
+    concurrency: ConcurrencyLimiter,
+    load_shedder: LoadShedder,
     auth_validator: AuthValidator,
+    identity_headers: Vec<(String, IdentityField)>,
+    client_certs: Arc<ClientCertRegistry>,
+    introspector: Option<Introspector>,
+    ip_rules: IpRules,
+    trusted_proxies: CidrTrie,
+    proxied_peers: Arc<ProxiedPeers>,
     routes: Vec<DbRoute>,
 }
 
+/// Rate limit key for a credential. Each auth mode has its own namespace so
+/// equal credentials from different modes don't share a counter.
+fn credential_key(auth_mode: AuthMode, credential: &str) -> String {
+    let namespace = match auth_mode {
+        AuthMode::None => "anonymous",
+        AuthMode::ApiKey => "apikey",
+        AuthMode::Basic => "basic",
+        AuthMode::Hmac => "hmac",
+        AuthMode::Mtls => "cert",
+        AuthMode::OAuth2 => "oauth2",
+    };
+    format!("{}:{}", namespace, credential)
+}
+
+impl CirithGateway {
+    /// Writes a response generated by the gateway itself, with the route's
+    /// CORS headers and the global response header rules applied as for
+    /// proxied responses.
+    async fn respond(
+        &self,
+        session: &mut Session,
+        ctx: &RequestContext,
+        mut header: ResponseHeader,
+        body: Option<Bytes>,
+    ) -> Result<()> {
+        let request = session.req_header();
+        if let Some(policy) = ctx.route.as_ref().and_then(|r| r.cors.as_ref())
+            && !cors::is_preflight(request)
+        {
+            cors::decorate(policy, request, &mut header)?;
+        }
+        let rules = &self.config.response_headers;
+        if rules.security_headers {
+            apply_security_headers(&mut header)?;
+        }
+        transform::apply(&rules.transforms, &mut header, |variable| {
+            ctx.variable(variable, request)
+        });
+
+        session.set_keepalive(None);
+        session
+            .write_response_header(Box::new(header), body.is_none())
+            .await?;
+        if let Some(body) = body {
+            session.write_response_body(Some(body), true).await?;
+        }
+        Ok(())
+    }
+
+    async fn write_error(
+        &self,
+        session: &mut Session,
+        ctx: &RequestContext,
+        mut header: ResponseHeader,
+        code: &str,
+    ) -> Result<()> {
+        let body = Bytes::from(format!(r#"{{"error":"{}"}}"#, code));
+        header.insert_header("Content-Type", "application/json")?;
+        header.insert_header("Content-Length", body.len().to_string())?;
+        self.respond(session, ctx, header, Some(body)).await
+    }
+
+    fn find_route(&self, path: &str) -> Option<&DbRoute> {
+        self.routes
+            .iter()
+            .filter(|r| path.starts_with(&r.path))
+            .max_by_key(|r| r.path.len())
+    }
+
+    fn client_ip(&self, session: &Session) -> Option<IpAddr> {
+        let peer = session.client_addr()?.as_inet()?;
+        let peer = self.proxied_peers.get(peer).unwrap_or(*peer).ip();
+        Some(resolve_client_ip(
+            peer,
+            &self.trusted_proxies,
+            self.config.server.forwarded_header,
+            session.req_header(),
+        ))
+    }
+
+    fn rate_limit_key(
+        &self,
+        key_by: &RateLimitKey,
+        session: &Session,
+        ctx: &RequestContext,
+    ) -> Option<String> {
+        let identity = ctx.identity.as_ref();
+        let key = match key_by {
+            RateLimitKey::Ip => None,
+            RateLimitKey::ApiKey => {
+                identity.map(|identity| credential_key(ctx.auth_mode, &identity.credential))
+            }
+            RateLimitKey::Consumer => identity.and_then(|identity| {
+                let consumer = identity.consumer.as_ref()?;
+                Some(format!("consumer:{}", consumer.name))
+            }),
+            RateLimitKey::Header(name) => session
+                .req_header()
+                .headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(|value| format!("header:{}", value)),
+            RateLimitKey::Route => ctx.route.as_ref().map(|r| format!("route:{}", r.path)),
+        };
+
+        key.or_else(|| ctx.client_ip.map(|ip| format!("ip:{}", ip)))
+    }
+
+    fn rate_limits(&self, session: &Session, ctx: &RequestContext) -> Vec<RateLimitCheck> {
+        let policies = self.rate_limit_policies.get();
+
+        let identity = ctx.identity.as_ref();
+        let consumer_limit = identity
+            .and_then(|identity| identity.consumer.as_ref())
+            .and_then(|consumer| {
+                let limit = policies.consumer(consumer.id)?;
+                Some((format!("consumer-override:{}", consumer.name), limit))
+            });
+        let identity_limit = identity.and_then(|identity| {
+            let limit = identity.rate_limit?;
+            Some((credential_key(ctx.auth_mode, &identity.credential), limit))
+        });
+
+        let api_key = identity
+            .filter(|_| ctx.auth_mode == AuthMode::ApiKey)
+            .map(|identity| identity.credential.as_str());
+        let policy_check = |name: String, prefix: Option<String>, policy: &RateLimitPolicy| {
+            if policy.exempt.matches(api_key, ctx.client_ip) {
+                return None;
+            }
+            let Some(key) = self.rate_limit_key(&policy.key_by, session, ctx) else {
+                tracing::warn!(policy = %name, "Could not get rate limit key");
+                return None;
+            };
+            Some(RateLimitCheck {
+                key: prefix.map_or(key.clone(), |prefix| format!("{}:{}", prefix, key)),
+                limit: policy.limit(),
+                policy: name,
+                shadow: policy.shadow,
+            })
+        };
+
+        let mut checks = Vec::new();
+        match identity_limit.or(consumer_limit) {
+            Some((key, limit)) => checks.push(RateLimitCheck {
+                policy: key.clone(),
+                key,
+                limit,
+                shadow: false,
+            }),
+            None => checks.extend(policy_check(
+                String::from("global"),
+                None,
+                policies.global(),
+            )),
+        }
+
+        if let Some(route) = &ctx.route
+            && let Some(policy) = policies.route(route.id)
+        {
+            checks.extend(policy_check(
+                format!("route:{}", route.path),
+                Some(format!("route:{}", route.id)),
+                policy,
+            ));
+        }
+
+        checks
+    }
+
+    fn quotas(&self, ctx: &RequestContext) -> Vec<(String, Quota)> {
+        let Some(identity) = ctx.identity.as_ref() else {
+            return Vec::new();
+        };
+
+        let mut quotas = Vec::new();
+        if let Some(quota) = identity.quota {
+            quotas.push((
+                QuotaSubject::ApiKey(&identity.credential).to_string(),
+                quota,
+            ));
+        }
+        if let Some(consumer) = &identity.consumer
+            && let Some(quota) = &consumer.quota
+        {
+            quotas.push((QuotaSubject::Consumer(&consumer.name).to_string(), quota.0));
+        }
+        quotas
+    }
+
+    fn concurrency_limits(
+        &self,
+        ctx: &RequestContext,
+    ) -> Vec<(ConcurrencyScope, String, ConcurrencyLimit)> {
+        let Some(route) = &ctx.route else {
+            return Vec::new();
+        };
+
+        let mut limits = Vec::new();
+        if let Some(limit) = &route.concurrency {
+            limits.push((ConcurrencyScope::Route, route.path.clone(), limit.0));
+        }
+        if let Some(limit) = self.config.concurrency.upstreams.get(&route.upstream) {
+            limits.push((ConcurrencyScope::Upstream, route.upstream.clone(), *limit));
+        }
+        limits
+    }
+
+    fn client_certificate(&self, session: &Session) -> Option<ClientCertificate> {
+        let ssl_digest = session.digest()?.ssl_digest.as_ref()?;
+        if ssl_digest.cert_digest.is_empty() {
+            return None;
+        }
+        self.client_certs.get(&ssl_digest.cert_digest)
+    }
+
+    async fn authenticate(
+        &self,
+        session: &Session,
+        ctx: &RequestContext,
+    ) -> std::result::Result<Option<Identity>, AuthError> {
+        let header = |name: &str| {
+            session
+                .req_header()
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+        };
+
+        match ctx.auth_mode {
+            AuthMode::None => Ok(None),
+            AuthMode::ApiKey => {
+                let key = header("x-api-key").ok_or("Missing API key")?;
+                let identity = self.auth_validator.validate(key).ok_or("Invalid API key")?;
+                Ok(Some(identity))
+            }
+            AuthMode::Basic => {
+                let authorization = header("authorization")
+                    .ok_or("Missing basic credentials")?
+                    .to_string();
+                let validator = self.auth_validator.clone();
+                let identity =
+                    tokio::task::spawn_blocking(move || validator.validate_basic(&authorization))
+                        .await
+                        .map_err(|_| AuthError::Unavailable("Basic authentication failed"))?
+                        .ok_or("Invalid basic credentials")?;
+                Ok(Some(identity))
+            }
+            AuthMode::Hmac => {
+                let request = session.req_header();
+                let path_and_query = request
+                    .uri
+                    .path_and_query()
+                    .map(|p| p.as_str())
+                    .unwrap_or("/");
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let identity = self.auth_validator.validate_signature(
+                    request.method.as_str(),
+                    path_and_query,
+                    header,
+                    now,
+                )?;
+                Ok(Some(identity))
+            }
+            AuthMode::Mtls => {
+                let client_cert = ctx
+                    .client_cert
+                    .as_ref()
+                    .ok_or("Missing client certificate")?;
+                let identity = self
+                    .auth_validator
+                    .validate_client_certificate(&client_cert.names())
+                    .ok_or("Unknown client certificate")?;
+                Ok(Some(identity))
+            }
+            AuthMode::OAuth2 => {
+                let introspector = self.introspector.as_ref().ok_or(AuthError::Unavailable(
+                    "Token introspection is not configured",
+                ))?;
+                let token = header("authorization")
+                    .and_then(bearer_token)
+                    .ok_or("Missing bearer token")?;
+                let info = introspector.introspect(token).await.map_err(|e| {
+                    tracing::warn!(error = %e, "Token introspection request failed");
+                    AuthError::Unavailable("Token introspection failed")
+                })?;
+                if !info.active {
+                    return Err("Inactive bearer token".into());
+                }
+
+                let required = ctx.route.as_ref().map(|r| r.scopes.as_slice());
+                if !info.has_scopes(required.unwrap_or_default()) {
+                    return Err(AuthError::Forbidden("Insufficient scope"));
+                }
+
+                Ok(Some(Identity {
+                    credential: info.subject().unwrap_or_default().to_string(),
+                    claims: info.claims(),
+                    consumer: info
+                        .client_id
+                        .as_deref()
+                        .and_then(|client_id| self.auth_validator.consumer_by_name(client_id)),
+                    ..Default::default()
+                }))
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl ProxyHttp for CirithGateway {
-    type CTX = String;
+    type CTX = RequestContext;
 
     fn new_ctx(&self) -> Self::CTX {
-        String::new()
+        RequestContext::default()
     }
 
     async fn upstream_peer(
@@ -32,13 +488,7 @@ impl ProxyHttp for CirithGateway {
         ctx: &mut Self::CTX,
     ) -> Result<Box<HttpPeer>> {
         let path = session.req_header().uri.path();
-        let route = self
-            .routes
-            .iter()
-            .filter(|r| path.starts_with(&r.path))
-            .max_by_key(|r| r.path.len());
-
-        match route {
+        match &ctx.route {
             Some(r) => {
                 tracing::info!(path = %path, upstream = %r.upstream, "Routing request");
 
@@ -49,7 +499,7 @@ impl ProxyHttp for CirithGateway {
                     .trim_start_matches("https://");
 
                 let port: u16 = if is_https { 443 } else { 80 };
-                *ctx = host.to_string();
+                ctx.host = host.to_string();
                 let peer = HttpPeer::new((host, port), is_https, host.to_string());
                 Ok(Box::new(peer))
             }
@@ -60,100 +510,485 @@ impl ProxyHttp for CirithGateway {
         }
     }
 
-    async fn request_filter(&self, session: &mut Session, _ctx: &mut Self::CTX) -> Result<bool>
+    async fn request_filter(&self, session: &mut Session, ctx: &mut Self::CTX) -> Result<bool>
     where
         Self::CTX: Send + Sync,
     {
+        // The internal listener only serves connections opened by the relay.
+        if self.config.server.proxy_protocol
+            && session
+                .client_addr()
+                .and_then(|addr| addr.as_inet())
+                .and_then(|addr| self.proxied_peers.get(addr))
+                .is_none()
+        {
+            tracing::warn!(peer = ?session.client_addr(), "Connection bypassed the PROXY protocol relay");
+            self.write_error(
+                session,
+                ctx,
+                ResponseHeader::build(400, None)?,
+                "proxy_protocol_required",
+            )
+            .await?;
+            return Ok(true);
+        }
+
         let path = session.req_header().uri.path();
         if path == "/health" {
             let header = ResponseHeader::build(200, None)?;
-            session.set_keepalive(None);
-            session
-                .write_response_header(Box::new(header), false)
+            self.respond(session, ctx, header, Some("OK".into()))
                 .await?;
 
-            session.write_response_body(Some("OK".into()), true).await?;
+            return Ok(true);
+        }
+
+        ctx.request_id = session
+            .req_header()
+            .headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|id| is_valid_request_id(id))
+            .map_or_else(generate_request_id, str::to_string);
+        ctx.route = self.find_route(path).cloned();
+        ctx.auth_mode = ctx
+            .route
+            .as_ref()
+            .and_then(|r| r.auth)
+            .unwrap_or_else(|| self.auth_validator.default_mode());
+
+        ctx.client_ip = self.client_ip(session);
+        if !self
+            .ip_rules
+            .is_allowed(ctx.client_ip, ctx.route.as_ref().map(|r| r.id))
+        {
+            tracing::warn!(ip = ?ctx.client_ip, path = %path, "Client IP blocked");
+
+            let header = ResponseHeader::build(403, None)?;
+            self.respond(session, ctx, header, None).await?;
 
             return Ok(true);
         }
 
-        if self.auth_validator.is_enabled() {
-            let api_key = session
-                .req_header()
-                .headers
-                .get("x-api-key")
-                .and_then(|v| v.to_str().ok());
-
-            match api_key {
-                Some(key) => {
-                    if !self.auth_validator.validate(key) {
-                        tracing::warn!("Invalid API key");
-
-                        let header = ResponseHeader::build(401, None)?;
-                        session.set_keepalive(None);
-                        session
-                            .write_response_header(Box::new(header), true)
-                            .await?;
+        if let Some(policy) = ctx.route.as_ref().and_then(|r| r.cors.as_ref())
+            && cors::is_preflight(session.req_header())
+        {
+            match cors::preflight(policy, session.req_header())? {
+                Some(header) => self.respond(session, ctx, header, None).await?,
+                None => {
+                    tracing::warn!(path = %path, "CORS preflight rejected");
+                    self.write_error(
+                        session,
+                        ctx,
+                        ResponseHeader::build(403, None)?,
+                        "cors_rejected",
+                    )
+                    .await?;
+                }
+            }
 
+            return Ok(true);
+        }
+
+        ctx.client_cert = self.client_certificate(session);
+        match self.authenticate(session, ctx).await {
+            Ok(identity) => {
+                if ctx.auth_mode == AuthMode::Hmac {
+                    let headers = &session.req_header().headers;
+                    let expected = headers
+                        .get(CONTENT_SHA256_HEADER)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    let too_large = headers
+                        .get("content-length")
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .is_some_and(|len| len > self.config.auth.signature.max_body_bytes);
+
+                    if too_large {
+                        tracing::warn!("Signed request body exceeds the buffering limit");
+                        self.write_error(
+                            session,
+                            ctx,
+                            ResponseHeader::build(413, None)?,
+                            "payload_too_large",
+                        )
+                        .await?;
                         return Ok(true);
                     }
+
+                    if session.is_body_empty() {
+                        if !ContentDigest::new().matches(&expected) {
+                            tracing::warn!("Request body does not match signed content digest");
+                            self.write_error(
+                                session,
+                                ctx,
+                                ResponseHeader::build(400, None)?,
+                                "body_digest_mismatch",
+                            )
+                            .await?;
+                            return Ok(true);
+                        }
+                    } else {
+                        ctx.signed_body = Some(SignedBody {
+                            digest: ContentDigest::new(),
+                            expected,
+                            buffer: BytesMut::new(),
+                        });
+                    }
                 }
-                None => {
-                    tracing::warn!("Missing API key");
-                    let header = ResponseHeader::build(401, None)?;
-                    session.set_keepalive(None);
-                    session
-                        .write_response_header(Box::new(header), false)
-                        .await?;
+                ctx.identity = identity;
+            }
+            Err(error) => {
+                let (status, reason) = match error {
+                    AuthError::Unauthorized(reason) => (401, reason),
+                    AuthError::Forbidden(reason) => (403, reason),
+                    AuthError::Unavailable(reason) => (503, reason),
+                };
+                tracing::warn!(mode = ?ctx.auth_mode, "{}", reason);
 
-                    return Ok(true);
+                let mut header = ResponseHeader::build(status, None)?;
+                match (ctx.auth_mode, status) {
+                    (AuthMode::Basic, 401) => {
+                        header.insert_header("WWW-Authenticate", "Basic realm=\"cirith\"")?;
+                    }
+                    (AuthMode::OAuth2, 401) => {
+                        header.insert_header("WWW-Authenticate", "Bearer realm=\"cirith\"")?;
+                    }
+                    (AuthMode::OAuth2, 403) => {
+                        header.insert_header(
+                            "WWW-Authenticate",
+                            "Bearer realm=\"cirith\", error=\"insufficient_scope\"",
+                        )?;
+                    }
+                    _ => {}
                 }
+                self.respond(session, ctx, header, None).await?;
+
+                return Ok(true);
             }
         }
 
-        let client_ip = session
-            .client_addr()
-            .map(|addr| addr.as_inet().map(|inet| inet.ip()))
-            .flatten();
+        for check in self.rate_limits(session, ctx) {
+            let decision = self.rate_limit.check(&check.key, check.limit).await;
+            if check.shadow {
+                if !decision.allowed {
+                    SHADOW_REJECTIONS
+                        .with_label_values(&[check.policy.as_str()])
+                        .inc();
+                    tracing::warn!(key = %check.key, policy = %check.policy, ip = ?ctx.client_ip, "Rate limit exceeded in shadow mode");
+                }
+                continue;
+            }
 
-        let ip = match client_ip {
-            Some(ip) => ip,
-            None => {
-                tracing::warn!("Could not get client IP");
-                return Ok(false);
+            if decision.allowed {
+                if ctx
+                    .rate_limit
+                    .is_none_or(|current| decision.remaining < current.remaining)
+                {
+                    ctx.rate_limit = Some(decision);
+                }
+                continue;
             }
-        };
 
-        if !self.rate_limit.check(ip) {
-            tracing::warn!(ip = %ip, "Rate limit exceeded");
+            let consumer = ctx
+                .identity
+                .as_ref()
+                .and_then(|identity| identity.consumer.as_ref())
+                .map(|consumer| consumer.name.as_str());
+            tracing::warn!(key = %check.key, policy = %check.policy, ip = ?ctx.client_ip, consumer = ?consumer, "Rate limit exceeded");
 
             let mut header = ResponseHeader::build(429, None)?;
-            header.insert_header(
-                "X-Rate-Limit-Limit",
-                self.config.rate_limit.max_requests.to_string(),
-            )?;
-
-            header.insert_header("X-Rate-Limit-Remaining", "0")?;
-            session.set_keepalive(None);
-            session
-                .write_response_header(Box::new(header), true)
+            insert_rate_limit_headers(&mut header, &decision)?;
+            self.write_error(session, ctx, header, "rate_limit_exceeded")
                 .await?;
 
             return Ok(true);
         }
 
+        if let Some(route) = &ctx.route
+            && let Some(config) = &route.load_shedding
+        {
+            let priority = Priority::of(ctx.identity.as_ref(), &config.premium_tags);
+            let Some(permit) = self.load_shedder.try_acquire(&route.path, config, priority) else {
+                tracing::warn!(
+                    route = %route.path,
+                    priority = ?priority,
+                    limit = ?self.load_shedder.limit(&route.path),
+                    "Shedding request"
+                );
+
+                let mut header = ResponseHeader::build(503, None)?;
+                header.insert_header("Retry-After", "1")?;
+                self.write_error(session, ctx, header, "overloaded").await?;
+
+                return Ok(true);
+            };
+            ctx.load_shedding = Some(permit);
+        }
+
+        for (scope, name, limit) in self.concurrency_limits(ctx) {
+            let Some(permit) = self.concurrency.acquire(scope, &name, limit).await else {
+                tracing::warn!(
+                    scope = ?scope,
+                    name = %name,
+                    in_flight = self.concurrency.in_flight(scope, &name),
+                    "Concurrency limit reached"
+                );
+
+                let mut header = ResponseHeader::build(503, None)?;
+                header.insert_header("Retry-After", "1")?;
+                self.write_error(session, ctx, header, "concurrency_limit_exceeded")
+                    .await?;
+
+                return Ok(true);
+            };
+            ctx.concurrency.push(permit);
+        }
+
+        let quotas = self.quotas(ctx);
+        if let Some(decision) = self.quotas.check(&quotas, chrono::Utc::now()) {
+            if !decision.allowed {
+                let subjects: Vec<&str> =
+                    quotas.iter().map(|(subject, _)| subject.as_str()).collect();
+                tracing::warn!(subjects = ?subjects, limit = decision.limit, "Quota exhausted");
+
+                let mut header = ResponseHeader::build(429, None)?;
+                insert_quota_headers(&mut header, &decision)?;
+                header.insert_header("Retry-After", decision.reset_secs.max(1).to_string())?;
+                self.write_error(session, ctx, header, "quota_exceeded")
+                    .await?;
+
+                return Ok(true);
+            }
+            ctx.quota = Some(decision);
+        }
+
         Ok(false)
     }
 
-    async fn upstream_request_filter(
+    async fn request_body_filter(
         &self,
         _session: &mut Session,
+        body: &mut Option<Bytes>,
+        end_of_stream: bool,
+        ctx: &mut Self::CTX,
+    ) -> Result<()>
+    where
+        Self::CTX: Send + Sync,
+    {
+        // Signed bodies are held back until the digest is verified, so an
+        // altered payload never reaches the upstream.
+        let Some(signed) = ctx.signed_body.as_mut() else {
+            return Ok(());
+        };
+
+        if let Some(chunk) = body.take() {
+            if signed.buffer.len() + chunk.len() > self.config.auth.signature.max_body_bytes {
+                tracing::warn!("Signed request body exceeds the buffering limit");
+                ctx.error = Some("payload_too_large");
+                return Err(pingora::Error::explain(
+                    ErrorType::HTTPStatus(413),
+                    "Signed body too large",
+                ));
+            }
+            signed.digest.update(&chunk);
+            signed.buffer.extend_from_slice(&chunk);
+        }
+
+        if !end_of_stream {
+            *body = Some(Bytes::new());
+            return Ok(());
+        }
+
+        let Some(signed) = ctx.signed_body.take() else {
+            return Ok(());
+        };
+        if !signed.digest.matches(&signed.expected) {
+            tracing::warn!("Request body does not match signed content digest");
+            ctx.error = Some("body_digest_mismatch");
+            return Err(pingora::Error::explain(
+                ErrorType::HTTPStatus(400),
+                "Body digest mismatch",
+            ));
+        }
+
+        *body = Some(signed.buffer.freeze());
+        Ok(())
+    }
+
+    async fn upstream_request_filter(
+        &self,
+        session: &mut Session,
         upstream_request: &mut RequestHeader,
         ctx: &mut Self::CTX,
     ) -> Result<()> {
-        upstream_request.insert_header("Host", ctx.as_str())?;
+        upstream_request.insert_header("Host", ctx.host.as_str())?;
+        if let Some(permit) = ctx.load_shedding.as_mut() {
+            permit.start();
+        }
+
+        // Route transforms run first so credential stripping and identity
+        // headers below always have the final say.
+        if let Some(transforms) = ctx.route.as_ref().and_then(|r| r.request_headers.as_ref()) {
+            let request = session.req_header();
+            transform::apply(transforms, upstream_request, |variable| {
+                ctx.variable(variable, request)
+            });
+        }
+
+        let forward_authorization = ctx.route.as_ref().is_some_and(|r| r.forward_authorization);
+        if !forward_authorization {
+            for name in credential_headers(ctx.auth_mode) {
+                upstream_request.remove_header(*name);
+            }
+        }
+
+        for (name, _) in &self.identity_headers {
+            upstream_request.remove_header(name.as_str());
+        }
+        upstream_request.remove_header(CLIENT_CERT_SUBJECT_HEADER);
+
+        if let Some(identity) = &ctx.identity {
+            for (name, field) in &self.identity_headers {
+                let Some(value) = field.resolve(identity, ctx.auth_mode) else {
+                    continue;
+                };
+                if upstream_request.insert_header(name.clone(), value).is_err() {
+                    tracing::warn!(header = %name, "Skipping invalid identity header value");
+                }
+            }
+        }
+
+        if let Some(subject) = ctx.client_cert.as_ref().and_then(|cert| cert.subject()) {
+            upstream_request.insert_header(CLIENT_CERT_SUBJECT_HEADER, subject)?;
+        }
+
+        Ok(())
+    }
+
+    fn upstream_response_filter(
+        &self,
+        _session: &mut Session,
+        upstream_response: &mut ResponseHeader,
+        ctx: &mut Self::CTX,
+    ) -> Result<()> {
+        if let Some(permit) = ctx.load_shedding.as_mut() {
+            permit.finish(matches!(upstream_response.status.as_u16(), 502..=504));
+        }
+        Ok(())
+    }
+
+    async fn response_filter(
+        &self,
+        session: &mut Session,
+        upstream_response: &mut ResponseHeader,
+        ctx: &mut Self::CTX,
+    ) -> Result<()> {
+        if let Some(decision) = &ctx.rate_limit {
+            insert_rate_limit_headers(upstream_response, decision)?;
+        }
+        if let Some(decision) = &ctx.quota {
+            insert_quota_headers(upstream_response, decision)?;
+        }
+
+        let request = session.req_header();
+        if let Some(policy) = ctx.route.as_ref().and_then(|r| r.cors.as_ref()) {
+            cors::decorate(policy, request, upstream_response)?;
+        }
+        let route_rules = ctx.route.as_ref().and_then(|r| r.response_headers.as_ref());
+        for rules in [
+            Some(&self.config.response_headers),
+            route_rules.map(|r| &r.0),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if rules.security_headers {
+                apply_security_headers(upstream_response)?;
+            }
+            transform::apply(&rules.transforms, upstream_response, |variable| {
+                ctx.variable(variable, request)
+            });
+        }
         Ok(())
     }
+
+    async fn fail_to_proxy(
+        &self,
+        session: &mut Session,
+        e: &pingora::Error,
+        ctx: &mut Self::CTX,
+    ) -> FailToProxy
+    where
+        Self::CTX: Send + Sync,
+    {
+        let (status, code) = match (e.etype(), e.esource()) {
+            (ErrorType::HTTPStatus(status), _) => (*status, "request_failed"),
+            (_, ErrorSource::Upstream) => (502, "bad_gateway"),
+            (
+                ErrorType::WriteError | ErrorType::ReadError | ErrorType::ConnectionClosed,
+                ErrorSource::Downstream,
+            ) => (0, ""),
+            (_, ErrorSource::Downstream) => (400, "bad_request"),
+            _ => (500, "internal_error"),
+        };
+
+        if status > 0 {
+            let code = ctx.error.take().unwrap_or(code);
+            let written = match ResponseHeader::build(status, None) {
+                Ok(header) => self.write_error(session, ctx, header, code).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                tracing::error!("Failed to send error response: {}", e);
+            }
+        }
+
+        FailToProxy {
+            error_code: status,
+            can_reuse_downstream: false,
+        }
+    }
+
+    async fn logging(&self, _session: &mut Session, e: Option<&pingora::Error>, ctx: &mut Self::CTX)
+    where
+        Self::CTX: Send + Sync,
+    {
+        if let Some(permit) = ctx.load_shedding.as_mut() {
+            permit.finish(e.is_some());
+        }
+    }
+}
+
+fn listen_address<S>(
+    server: &mut Server,
+    proxy: &mut PreboundListeners<S>,
+    port: u16,
+    proxied_peers: Option<&Arc<ProxiedPeers>>,
+    trusted_proxies: &CidrTrie,
+) -> String {
+    let address = format!("0.0.0.0:{}", port);
+    let Some(proxied_peers) = proxied_peers else {
+        return address;
+    };
+
+    let internal = proxy
+        .bind_loopback()
+        .expect("Failed to bind internal listener");
+    let relay = ProxyProtocolRelay::bind(
+        &address,
+        internal,
+        proxied_peers.clone(),
+        trusted_proxies.clone(),
+    )
+    .expect("Failed to bind PROXY protocol listener");
+    server.add_service(background_service(
+        &format!("PROXY protocol {}", port),
+        relay,
+    ));
+    tracing::info!(internal = %internal, "Accepting PROXY protocol on {}", address);
+    internal.to_string()
 }
 
 fn main() {
@@ -162,35 +997,184 @@ fn main() {
 
     let config = Config::load("config.yml").expect("Failed to load config");
     let port = config.server.gateway_port;
+    let metrics_port = config.server.metrics_port;
 
     let mut server = Server::new(None).unwrap();
     server.bootstrap();
 
-    let rate_limit = RateLimiter::new(
-        config.rate_limit.max_requests,
-        config.rate_limit.window_secs,
-    );
+    let rate_limit = match &config.rate_limit.redis {
+        Some(redis) => {
+            let store = RedisStore::new(redis).expect("Invalid Redis URL");
+            tracing::info!(mode = ?redis.failure_mode, "Using Redis rate limit store");
+            RateLimiter::new(Arc::new(store), redis.failure_mode)
+        }
+        None => {
+            let store = Arc::new(MemoryStore::new(config.rate_limit.max_keys));
+            server.add_service(background_service(
+                "rate limit eviction",
+                RateLimitEviction::new(store.clone()),
+            ));
+            RateLimiter::new(store, FailureMode::Closed)
+        }
+    };
+
+    let cipher = config
+        .auth
+        .encryption_key
+        .as_deref()
+        .map(SecretCipher::new)
+        .transpose()
+        .expect("Invalid encryption key");
 
     let rt = tokio::runtime::Runtime::new().unwrap();
-    let routes = rt
+    let (database, routes, ip_rules, auth_validator, quota_usage, policies) = rt
         .block_on(async {
             let database = Database::new(&config.database.url).await?;
-            database.get_routes().await
+            let routes = database.get_routes().await?;
+            let ip_rules = IpRules::new(&database.get_ip_rules().await?);
+            let auth_validator = AuthValidator::new(&config.auth)
+                .with_database_keys(
+                    database.get_consumers().await?,
+                    database.get_api_keys().await?,
+                )
+                .with_basic_credentials(database.get_basic_auth_credentials().await?)
+                .with_hmac_credentials(database.get_hmac_credentials().await?, cipher.as_ref())
+                .with_client_certificates(database.get_client_certificates().await?);
+            let quota_usage = database.get_quota_usage().await?;
+            let policies = RateLimitPolicies::new(
+                database
+                    .get_global_rate_limit()
+                    .await?
+                    .unwrap_or_else(|| config.rate_limit.policy()),
+                &routes,
+                &database.get_consumers().await?,
+            );
+            Ok::<_, Box<dyn std::error::Error>>((
+                Arc::new(database),
+                routes,
+                ip_rules,
+                auth_validator,
+                quota_usage,
+                policies,
+            ))
         })
         .unwrap();
 
-    let auth_validator = AuthValidator::new(&config.auth);
+    let quotas = Arc::new(QuotaTracker::new(quota_usage));
+    server.add_service(background_service(
+        "quota flush",
+        QuotaFlush::new(
+            quotas.clone(),
+            database.clone(),
+            Duration::from_secs(config.rate_limit.quota_flush_secs),
+        ),
+    ));
+
+    server.add_service(background_service(
+        "api key migration",
+        KeyMigrationFlush::new(
+            auth_validator.clone(),
+            database.clone(),
+            Duration::from_secs(KEY_MIGRATION_FLUSH_SECS),
+        ),
+    ));
+
+    let rate_limit_policies = Arc::new(PolicyStore::new(policies));
+    server.add_service(background_service(
+        "rate limit policy reload",
+        PolicyReload::new(
+            rate_limit_policies.clone(),
+            database,
+            config.rate_limit.policy(),
+            Duration::from_secs(config.rate_limit.reload_secs),
+        ),
+    ));
+
+    let identity_headers = config
+        .auth
+        .identity_headers
+        .iter()
+        .map(|(name, field)| {
+            let field = field.parse().expect("Invalid identity header field");
+            (name.clone(), field)
+        })
+        .collect();
+    let trusted_proxies: CidrTrie = config
+        .server
+        .trusted_proxies
+        .iter()
+        .map(|proxy| proxy.parse().expect("Invalid trusted proxy"))
+        .collect();
+    let introspector = config
+        .auth
+        .introspection
+        .clone()
+        .map(Introspector::new)
+        .transpose()
+        .expect("Failed to build introspection client");
+    let client_certs = Arc::new(ClientCertRegistry::new());
+    let proxied_peers = Arc::new(ProxiedPeers::new());
+    let proxy_protocol = config.server.proxy_protocol.then_some(&proxied_peers);
+    let tls = config.server.tls.clone();
+    let relay_trusted_proxies = trusted_proxies.clone();
     let gateway = CirithGateway {
         config,
         rate_limit,
+        rate_limit_policies,
+        quotas,
+        concurrency: ConcurrencyLimiter::new(),
+        load_shedder: LoadShedder::new(),
         auth_validator,
+        identity_headers,
+        client_certs: client_certs.clone(),
+        introspector,
+        ip_rules,
+        trusted_proxies,
+        proxied_peers: proxied_peers.clone(),
         routes,
     };
 
-    let mut proxy = pingora_proxy::http_proxy_service(&server.configuration, gateway);
-    proxy.add_tcp(&format!("0.0.0.0:{}", port));
+    let mut proxy = PreboundListeners::new(pingora_proxy::http_proxy_service(
+        &server.configuration,
+        gateway,
+    ));
+    let address = listen_address(
+        &mut server,
+        &mut proxy,
+        port,
+        proxy_protocol,
+        &relay_trusted_proxies,
+    );
+    proxy.inner_mut().add_tcp(&address);
     tracing::info!("Listening on 0.0.0.0:{}", port);
 
+    if let Some(tls) = tls {
+        let settings = mtls::tls_settings(&tls, client_certs).expect("Failed to configure TLS");
+        let address = listen_address(
+            &mut server,
+            &mut proxy,
+            tls.port,
+            proxy_protocol,
+            &relay_trusted_proxies,
+        );
+        proxy
+            .inner_mut()
+            .add_tls_with_settings(&address, None, settings);
+        tracing::info!(
+            mtls = tls.client_ca_path.is_some(),
+            "Listening with TLS on 0.0.0.0:{}",
+            tls.port
+        );
+    }
+
     server.add_service(proxy);
+
+    if let Some(metrics_port) = metrics_port {
+        let mut metrics = Service::prometheus_http_service();
+        metrics.add_tcp(&format!("0.0.0.0:{}", metrics_port));
+        server.add_service(metrics);
+        tracing::info!("Serving metrics on 0.0.0.0:{}", metrics_port);
+    }
+
     server.run_forever();
 }
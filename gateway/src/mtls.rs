@@ -0,0 +1,430 @@
+use pingora::listeners::tls::TlsSettings;
+use pingora::tls::hash::MessageDigest;
+use pingora::tls::nid::Nid;
+use pingora::tls::ssl::{
+    ClientHelloResponse, Ssl, SslAcceptorBuilder, SslOptions, SslSessionCacheMode, SslVerifyMode,
+};
+use pingora::tls::x509::{X509Name, X509Ref, X509StoreContext};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+// imports
+use cirith_shared::config::TlsConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    pub common_name: Option<String>,
+    pub subject_alt_names: Vec<String>,
+}
+
+impl ClientCertificate {
+    pub fn from_x509(cert: &X509Ref) -> Self {
+        let common_name = cert
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .and_then(|entry| entry.data().to_string().ok());
+
+        let subject_alt_names = cert
+            .subject_alt_names()
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| name.dnsname().or(name.email()).or(name.uri()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            common_name,
+            subject_alt_names,
+        }
+    }
+
+    pub fn subject(&self) -> Option<&str> {
+        self.common_name
+            .as_deref()
+            .or(self.subject_alt_names.first().map(String::as_str))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.common_name
+            .iter()
+            .chain(self.subject_alt_names.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct TrackedCertificate {
+    certificate: ClientCertificate,
+    connections: usize,
+}
+
+/// Certificates of live TLS connections, keyed by SHA-256 digest. Each
+/// connection holds a reference that is released when OpenSSL frees it.
+#[derive(Debug, Default)]
+pub struct ClientCertRegistry {
+    certificates: Mutex<HashMap<Vec<u8>, TrackedCertificate>>,
+}
+
+impl ClientCertRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn acquire(&self, digest: &[u8], cert: &X509Ref) {
+        let Ok(mut certificates) = self.certificates.lock() else {
+            return;
+        };
+
+        certificates
+            .entry(digest.to_vec())
+            .or_insert_with(|| TrackedCertificate {
+                certificate: ClientCertificate::from_x509(cert),
+                connections: 0,
+            })
+            .connections += 1;
+    }
+
+    pub fn release(&self, digest: &[u8]) {
+        let Ok(mut certificates) = self.certificates.lock() else {
+            return;
+        };
+
+        if let Some(tracked) = certificates.get_mut(digest) {
+            tracked.connections -= 1;
+            if tracked.connections == 0 {
+                certificates.remove(digest);
+            }
+        }
+    }
+
+    pub fn get(&self, digest: &[u8]) -> Option<ClientCertificate> {
+        let certificates = self.certificates.lock().ok()?;
+        certificates
+            .get(digest)
+            .map(|tracked| tracked.certificate.clone())
+    }
+}
+
+struct ConnectionCertificate {
+    registry: Arc<ClientCertRegistry>,
+    digest: OnceLock<Vec<u8>>,
+}
+
+impl ConnectionCertificate {
+    fn record(&self, cert: &X509Ref) {
+        let Ok(digest) = cert.digest(MessageDigest::sha256()) else {
+            return;
+        };
+        if self.digest.set(digest.to_vec()).is_ok() {
+            self.registry.acquire(digest.as_ref(), cert);
+        }
+    }
+}
+
+impl Drop for ConnectionCertificate {
+    fn drop(&mut self) {
+        if let Some(digest) = self.digest.get() {
+            self.registry.release(digest);
+        }
+    }
+}
+
+pub fn tls_settings(
+    config: &TlsConfig,
+    registry: Arc<ClientCertRegistry>,
+) -> Result<TlsSettings, Box<dyn std::error::Error>> {
+    let mut settings = TlsSettings::intermediate(&config.cert_path, &config.key_path)?;
+    configure_client_auth(&mut settings, config, registry)?;
+    Ok(settings)
+}
+
+fn configure_client_auth(
+    builder: &mut SslAcceptorBuilder,
+    config: &TlsConfig,
+    registry: Arc<ClientCertRegistry>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(ca_path) = &config.client_ca_path else {
+        return Ok(());
+    };
+
+    builder.set_ca_file(ca_path)?;
+    builder.set_client_ca_list(X509Name::load_client_ca_file(ca_path)?);
+
+    // A resumed session skips certificate verification, so the connection
+    // would never be recorded in the registry. Always do a full handshake.
+    builder.set_session_cache_mode(SslSessionCacheMode::OFF);
+    builder.set_options(SslOptions::NO_TICKET);
+    builder.set_num_tickets(0)?;
+
+    let mut mode = SslVerifyMode::PEER;
+    if config.require_client_cert {
+        mode |= SslVerifyMode::FAIL_IF_NO_PEER_CERT;
+    }
+
+    let connection_index = Ssl::new_ex_index::<ConnectionCertificate>()?;
+    let ssl_index = X509StoreContext::ssl_idx()?;
+    builder.set_client_hello_callback(move |ssl, _| {
+        ssl.set_ex_data(
+            connection_index,
+            ConnectionCertificate {
+                registry: registry.clone(),
+                digest: OnceLock::new(),
+            },
+        );
+        Ok(ClientHelloResponse::SUCCESS)
+    });
+    builder.set_verify_callback(mode, move |preverified, context| {
+        if preverified
+            && context.error_depth() == 0
+            && let Some(cert) = context.current_cert()
+            && let Some(connection) = context
+                .ex_data(ssl_index)
+                .and_then(|ssl| ssl.ex_data(connection_index))
+        {
+            connection.record(cert);
+        }
+        preverified
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::pkey::{PKey, Private};
+    use openssl::ssl::{SslAcceptor, SslConnector, SslMethod};
+    use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+    use openssl::x509::{X509, X509NameBuilder};
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::{Path, PathBuf};
+    use std::thread;
+
+    struct Issued {
+        cert: X509,
+        key: PKey<Private>,
+    }
+
+    fn generate_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn issue(common_name: &str, san: Option<&str>, issuer: Option<&Issued>) -> Issued {
+        let key = generate_key();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name)
+            .unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = BigNum::from_u32(serial_number()).unwrap().to_asn1_integer();
+        builder.set_serial_number(&serial.unwrap()).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.set_pubkey(&key).unwrap();
+
+        match issuer {
+            Some(issuer) => {
+                builder.set_issuer_name(issuer.cert.subject_name()).unwrap();
+                if let Some(san) = san {
+                    let extension = SubjectAlternativeName::new()
+                        .dns(san)
+                        .build(&builder.x509v3_context(Some(&issuer.cert), None))
+                        .unwrap();
+                    builder.append_extension(extension).unwrap();
+                }
+                builder.sign(&issuer.key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                builder.set_issuer_name(&name).unwrap();
+                builder
+                    .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+                    .unwrap();
+                builder.sign(&key, MessageDigest::sha256()).unwrap();
+            }
+        }
+
+        Issued {
+            cert: builder.build(),
+            key,
+        }
+    }
+
+    fn serial_number() -> u32 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos()
+    }
+
+    fn write_pem(name: &str, cert: &X509) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("cirith-mtls-{}-{}.pem", std::process::id(), name));
+        std::fs::write(&path, cert.to_pem().unwrap()).unwrap();
+        path
+    }
+
+    fn acceptor(ca: &Issued, ca_path: &Path, registry: Arc<ClientCertRegistry>) -> SslAcceptor {
+        let server = issue("localhost", Some("localhost"), Some(ca));
+        let config = TlsConfig {
+            port: 0,
+            cert_path: String::new(),
+            key_path: String::new(),
+            client_ca_path: Some(ca_path.to_string_lossy().to_string()),
+            require_client_cert: true,
+        };
+
+        let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        builder.set_certificate(&server.cert).unwrap();
+        builder.set_private_key(&server.key).unwrap();
+        builder.set_session_id_context(b"cirith").unwrap();
+        configure_client_auth(&mut builder, &config, registry).unwrap();
+        builder.build()
+    }
+
+    fn connector(client: &Issued) -> SslConnector {
+        let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        connector.set_certificate(&client.cert).unwrap();
+        connector.set_private_key(&client.key).unwrap();
+        connector.build()
+    }
+
+    fn handshake(client: &Issued, ca: &Issued, test_name: &str) -> Option<ClientCertificate> {
+        let ca_path = write_pem(test_name, &ca.cert);
+        let registry = Arc::new(ClientCertRegistry::new());
+        let acceptor = acceptor(ca, &ca_path, registry.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_thread = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let stream = acceptor.accept(stream).ok()?;
+            let digest = stream
+                .ssl()
+                .peer_certificate()?
+                .digest(MessageDigest::sha256())
+                .ok()?;
+            let certificate = registry.get(digest.as_ref());
+            drop(stream);
+            assert!(registry.get(digest.as_ref()).is_none());
+            certificate
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let _ = connector(client).connect("localhost", stream);
+
+        let result = server_thread.join().unwrap();
+        std::fs::remove_file(ca_path).ok();
+        result
+    }
+
+    #[test]
+    fn test_client_certificate_from_trusted_ca() {
+        let ca = issue("Cirith Test CA", None, None);
+        let client = issue("partner", Some("partner.example.com"), Some(&ca));
+
+        let certificate = handshake(&client, &ca, "trusted").unwrap();
+        assert_eq!(certificate.subject(), Some("partner"));
+        assert_eq!(
+            certificate.names(),
+            vec![String::from("partner"), String::from("partner.example.com")]
+        );
+    }
+
+    #[test]
+    fn test_client_certificate_from_untrusted_ca_is_rejected() {
+        let ca = issue("Cirith Test CA", None, None);
+        let other_ca = issue("Other CA", None, None);
+        let client = issue("intruder", None, Some(&other_ca));
+
+        assert!(handshake(&client, &ca, "untrusted").is_none());
+    }
+
+    #[test]
+    fn test_session_resumption_still_records_certificate() {
+        let ca = issue("Cirith Test CA", None, None);
+        let client = issue("partner", None, Some(&ca));
+        let ca_path = write_pem("resumed", &ca.cert);
+        let registry = Arc::new(ClientCertRegistry::new());
+        let acceptor = acceptor(&ca, &ca_path, registry.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_thread = thread::spawn(move || {
+            (0..2)
+                .map(|_| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut stream = acceptor.accept(stream).unwrap();
+                    let digest = stream
+                        .ssl()
+                        .peer_certificate()
+                        .map(|cert| cert.digest(MessageDigest::sha256()).unwrap());
+                    let certificate = digest.and_then(|digest| registry.get(digest.as_ref()));
+                    stream.write_all(b"x").unwrap();
+                    let _ = stream.shutdown();
+                    certificate
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let connector = connector(&client);
+        let mut first = connector
+            .connect("localhost", TcpStream::connect(addr).unwrap())
+            .unwrap();
+        first.read_exact(&mut [0; 1]).unwrap();
+        let session = first.ssl().session().unwrap().to_owned();
+
+        let mut ssl = connector
+            .configure()
+            .unwrap()
+            .into_ssl("localhost")
+            .unwrap();
+        unsafe { ssl.set_session(&session).unwrap() };
+        let mut second = ssl.connect(TcpStream::connect(addr).unwrap()).unwrap();
+        second.read_exact(&mut [0; 1]).unwrap();
+        assert!(!second.ssl().session_reused());
+
+        let certificates = server_thread.join().unwrap();
+        std::fs::remove_file(ca_path).ok();
+        for certificate in certificates {
+            assert_eq!(certificate.unwrap().subject(), Some("partner"));
+        }
+    }
+
+    #[test]
+    fn test_registry_releases_certificate_with_last_connection() {
+        let ca = issue("Cirith Test CA", None, None);
+        let client = issue("partner", None, Some(&ca));
+        let registry = ClientCertRegistry::new();
+        let digest = client.cert.digest(MessageDigest::sha256()).unwrap();
+
+        assert!(registry.get(digest.as_ref()).is_none());
+        registry.acquire(digest.as_ref(), &client.cert);
+        registry.acquire(digest.as_ref(), &client.cert);
+        assert_eq!(
+            registry.get(digest.as_ref()).unwrap().subject(),
+            Some("partner")
+        );
+
+        registry.release(digest.as_ref());
+        assert!(registry.get(digest.as_ref()).is_some());
+        registry.release(digest.as_ref());
+        assert!(registry.get(digest.as_ref()).is_none());
+    }
+}
@@ -0,0 +1,453 @@
+use async_trait::async_trait;
+use pingora::server::{Fds, ListenFds, ShutdownWatch};
+use pingora::services::Service;
+use pingora::services::background::BackgroundService;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::fd::IntoRawFd;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+// imports
+use cirith_shared::ip_filter::CidrTrie;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LENGTH: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEADER_LENGTH: usize = 16;
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyHeader {
+    pub source: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseResult {
+    Incomplete,
+    Complete(ProxyHeader, usize),
+}
+
+pub fn parse_header(buf: &[u8]) -> Result<ParseResult, &'static str> {
+    let prefix_length = buf.len().min(V2_SIGNATURE.len());
+    if buf[..prefix_length] == V2_SIGNATURE[..prefix_length] {
+        return parse_v2(buf);
+    }
+
+    let prefix_length = buf.len().min(V1_PREFIX.len());
+    if buf[..prefix_length] == V1_PREFIX[..prefix_length] {
+        return parse_v1(buf);
+    }
+
+    Err("Missing PROXY protocol header")
+}
+
+fn parse_v1(buf: &[u8]) -> Result<ParseResult, &'static str> {
+    let Some(end) = buf.windows(2).position(|w| w == b"\r\n") else {
+        if buf.len() >= V1_MAX_LENGTH {
+            return Err("PROXY v1 header too long");
+        }
+        return Ok(ParseResult::Incomplete);
+    };
+
+    let line = std::str::from_utf8(&buf[..end]).map_err(|_| "Invalid PROXY v1 header")?;
+    let fields: Vec<&str> = line.split(' ').collect();
+
+    let source = match fields.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => None,
+        ["PROXY", "TCP4" | "TCP6", source, _, port, _] => {
+            let ip: IpAddr = source.parse().map_err(|_| "Invalid PROXY v1 source")?;
+            let port: u16 = port.parse().map_err(|_| "Invalid PROXY v1 port")?;
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err("Invalid PROXY v1 header"),
+    };
+
+    Ok(ParseResult::Complete(ProxyHeader { source }, end + 2))
+}
+
+fn parse_v2(buf: &[u8]) -> Result<ParseResult, &'static str> {
+    if buf.len() < V2_HEADER_LENGTH {
+        return Ok(ParseResult::Incomplete);
+    }
+
+    let version_command = buf[12];
+    if version_command >> 4 != 2 {
+        return Err("Unsupported PROXY protocol version");
+    }
+
+    let length = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    let total = V2_HEADER_LENGTH + length;
+    if buf.len() < total {
+        return Ok(ParseResult::Incomplete);
+    }
+
+    let addresses = &buf[V2_HEADER_LENGTH..total];
+    let source = match (version_command & 0x0f, buf[13] >> 4) {
+        (0x00, _) => None,
+        (0x01, 0x1) if addresses.len() >= 12 => {
+            let ip = Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Some(SocketAddr::new(IpAddr::V4(ip), port))
+        }
+        (0x01, 0x2) if addresses.len() >= 36 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&addresses[..16]);
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+        }
+        (0x01, _) => None,
+        _ => return Err("Unsupported PROXY v2 command"),
+    };
+
+    Ok(ParseResult::Complete(ProxyHeader { source }, total))
+}
+
+#[derive(Debug, Default)]
+pub struct ProxiedPeers {
+    peers: Mutex<HashMap<SocketAddr, SocketAddr>>,
+}
+
+impl ProxiedPeers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, relay_addr: &SocketAddr) -> Option<SocketAddr> {
+        self.peers.lock().ok()?.get(relay_addr).copied()
+    }
+
+    fn insert(&self, relay_addr: SocketAddr, source: SocketAddr) {
+        if let Ok(mut peers) = self.peers.lock() {
+            peers.insert(relay_addr, source);
+        }
+    }
+
+    fn remove(&self, relay_addr: &SocketAddr) {
+        if let Ok(mut peers) = self.peers.lock() {
+            peers.remove(relay_addr);
+        }
+    }
+}
+
+/// Pingora has no hook between accept and the HTTP parser, so PROXY headers
+/// are read here and the connection is relayed to an internal listener.
+pub struct ProxyProtocolRelay {
+    listener: Mutex<Option<std::net::TcpListener>>,
+    upstream: SocketAddr,
+    peers: Arc<ProxiedPeers>,
+    trusted_proxies: CidrTrie,
+}
+
+impl ProxyProtocolRelay {
+    pub fn bind(
+        addr: &str,
+        upstream: SocketAddr,
+        peers: Arc<ProxiedPeers>,
+        trusted_proxies: CidrTrie,
+    ) -> std::io::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener: Mutex::new(Some(listener)),
+            upstream,
+            peers,
+            trusted_proxies,
+        })
+    }
+
+    async fn run(&self, listener: TcpListener, mut shutdown: ShutdownWatch) {
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok((_, peer)) if !self.trusted_proxies.contains(peer.ip()) => {
+                        tracing::warn!(peer = %peer, "Rejected PROXY protocol connection from untrusted peer");
+                    }
+                    Ok((stream, peer)) => {
+                        let upstream = self.upstream;
+                        let peers = self.peers.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = relay(stream, peer, upstream, peers).await {
+                                tracing::warn!(peer = %peer, "PROXY protocol connection failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => tracing::warn!("Failed to accept connection: {}", e),
+                },
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BackgroundService for ProxyProtocolRelay {
+    async fn start(&self, shutdown: ShutdownWatch) {
+        let listener = self.listener.lock().ok().and_then(|mut l| l.take());
+        let Some(listener) = listener else {
+            return;
+        };
+        match TcpListener::from_std(listener) {
+            Ok(listener) => self.run(listener, shutdown).await,
+            Err(e) => tracing::error!("Failed to start PROXY protocol listener: {}", e),
+        }
+    }
+}
+
+async fn relay(
+    mut downstream: TcpStream,
+    peer: SocketAddr,
+    upstream: SocketAddr,
+    peers: Arc<ProxiedPeers>,
+) -> std::io::Result<()> {
+    let (header, buffered) = tokio::time::timeout(HEADER_TIMEOUT, read_header(&mut downstream))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "PROXY header timeout"))??;
+
+    let mut upstream = TcpStream::connect(upstream).await?;
+    let relay_addr = upstream.local_addr()?;
+    peers.insert(relay_addr, header.source.unwrap_or(peer));
+
+    let result = async {
+        upstream.write_all(&buffered).await?;
+        tokio::io::copy_bidirectional(&mut downstream, &mut upstream).await
+    }
+    .await;
+
+    peers.remove(&relay_addr);
+    result.map(|_| ())
+}
+
+async fn read_header(stream: &mut TcpStream) -> std::io::Result<(ProxyHeader, Vec<u8>)> {
+    let mut buf = Vec::with_capacity(256);
+    loop {
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        match parse_header(&buf) {
+            Ok(ParseResult::Incomplete) => continue,
+            Ok(ParseResult::Complete(header, consumed)) => {
+                return Ok((header, buf.split_off(consumed)));
+            }
+            Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
+    }
+}
+
+/// Wraps a service whose internal listeners are bound up front and handed to
+/// pingora through its listener fd table, so the port is never released.
+pub struct PreboundListeners<S> {
+    inner: S,
+    listeners: Vec<(String, std::net::TcpListener)>,
+}
+
+impl<S> PreboundListeners<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            listeners: Vec::new(),
+        }
+    }
+
+    pub fn bind_loopback(&mut self) -> std::io::Result<SocketAddr> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        self.listeners.push((addr.to_string(), listener));
+        Ok(addr)
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+#[async_trait]
+impl<S: Service> Service for PreboundListeners<S> {
+    async fn start_service(
+        &mut self,
+        fds: Option<ListenFds>,
+        shutdown: ShutdownWatch,
+        listeners_per_fd: usize,
+    ) {
+        let fds = fds.unwrap_or_else(|| Arc::new(tokio::sync::Mutex::new(Fds::new())));
+        {
+            let mut table = fds.lock().await;
+            for (addr, listener) in self.listeners.drain(..) {
+                table.add(addr, listener.into_raw_fd());
+            }
+        }
+        self.inner
+            .start_service(Some(fds), shutdown, listeners_per_fd)
+            .await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn threads(&self) -> Option<usize> {
+        self.inner.threads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2_header(command: u8, family: u8, addresses: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(0x20 | command);
+        header.push(family);
+        header.extend_from_slice(&(addresses.len() as u16).to_be_bytes());
+        header.extend_from_slice(addresses);
+        header
+    }
+
+    #[test]
+    fn test_parse_v1_tcp4() {
+        let buf = b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 443\r\nGET / HTTP/1.1\r\n";
+        let ParseResult::Complete(header, consumed) = parse_header(buf).unwrap() else {
+            panic!("header should be complete");
+        };
+        assert_eq!(header.source, Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(&buf[consumed..], b"GET / HTTP/1.1\r\n");
+    }
+
+    #[test]
+    fn test_parse_v1_tcp6_and_unknown() {
+        let buf = b"PROXY TCP6 2001:db8::1 2001:db8::2 4711 443\r\n";
+        let ParseResult::Complete(header, _) = parse_header(buf).unwrap() else {
+            panic!("header should be complete");
+        };
+        assert_eq!(header.source, Some("[2001:db8::1]:4711".parse().unwrap()));
+
+        let ParseResult::Complete(header, _) = parse_header(b"PROXY UNKNOWN\r\n").unwrap() else {
+            panic!("header should be complete");
+        };
+        assert_eq!(header.source, None);
+    }
+
+    #[test]
+    fn test_parse_incomplete_and_invalid() {
+        assert_eq!(parse_header(b"PRO"), Ok(ParseResult::Incomplete));
+        assert_eq!(
+            parse_header(b"PROXY TCP4 1.2.3.4"),
+            Ok(ParseResult::Incomplete)
+        );
+        assert_eq!(parse_header(b"\r\n\r\n\0"), Ok(ParseResult::Incomplete));
+        assert!(parse_header(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 nope 10.0.0.1 1 2\r\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_v2_tcp4() {
+        let mut addresses = vec![203, 0, 113, 7, 10, 0, 0, 1];
+        addresses.extend_from_slice(&51234u16.to_be_bytes());
+        addresses.extend_from_slice(&443u16.to_be_bytes());
+        let mut buf = v2_header(0x1, 0x11, &addresses);
+        let header_length = buf.len();
+        buf.extend_from_slice(b"payload");
+
+        let ParseResult::Complete(header, consumed) = parse_header(&buf).unwrap() else {
+            panic!("header should be complete");
+        };
+        assert_eq!(header.source, Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(consumed, header_length);
+        assert_eq!(
+            parse_header(&buf[..header_length - 1]),
+            Ok(ParseResult::Incomplete)
+        );
+    }
+
+    #[test]
+    fn test_parse_v2_local() {
+        let ParseResult::Complete(header, consumed) =
+            parse_header(&v2_header(0x0, 0x00, &[])).unwrap()
+        else {
+            panic!("header should be complete");
+        };
+        assert_eq!(header.source, None);
+        assert_eq!(consumed, V2_HEADER_LENGTH);
+    }
+
+    #[tokio::test]
+    async fn test_relay_records_original_client_address() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        let peers = Arc::new(ProxiedPeers::new());
+
+        let downstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let downstream_addr = downstream.local_addr().unwrap();
+        let relay_peers = peers.clone();
+        tokio::spawn(async move {
+            let (stream, peer) = downstream.accept().await.unwrap();
+            relay(stream, peer, upstream_addr, relay_peers).await.ok();
+        });
+
+        let mut client = TcpStream::connect(downstream_addr).await.unwrap();
+        client
+            .write_all(b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 80\r\nhello")
+            .await
+            .unwrap();
+
+        let (mut stream, relay_addr) = upstream.accept().await.unwrap();
+        let mut received = [0u8; 5];
+        stream.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"hello");
+        assert_eq!(
+            peers.get(&relay_addr),
+            Some("203.0.113.7:51234".parse().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_relay_rejects_untrusted_peer() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let trusted: CidrTrie = ["10.0.0.0/8".parse().unwrap()].into_iter().collect();
+        let relay = Arc::new(
+            ProxyProtocolRelay::bind(
+                "127.0.0.1:0",
+                upstream.local_addr().unwrap(),
+                Arc::new(ProxiedPeers::new()),
+                trusted,
+            )
+            .unwrap(),
+        );
+        let relay_addr = relay
+            .listener
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let (_shutdown, watch) = tokio::sync::watch::channel(false);
+        let running = relay.clone();
+        tokio::spawn(async move { running.start(watch).await });
+
+        let mut client = TcpStream::connect(relay_addr).await.unwrap();
+        client
+            .write_all(b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 80\r\nhello")
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.ok();
+        assert!(received.is_empty());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), upstream.accept())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_prebound_listener_keeps_port_reserved() {
+        let mut listeners = PreboundListeners::new(());
+        let addr = listeners.bind_loopback().unwrap();
+
+        assert!(std::net::TcpListener::bind(addr).is_err());
+        assert_eq!(listeners.listeners[0].0, addr.to_string());
+    }
+}
@@ -0,0 +1,367 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use pingora::server::ShutdownWatch;
+use pingora::services::background::BackgroundService;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+// imports
+use cirith_shared::quota::Quota;
+use cirith_shared::storage::{Database, DbQuotaUsage};
+
+struct Counter {
+    period_start: NaiveDate,
+    used: u64,
+    pending: u64,
+}
+
+impl Counter {
+    fn new(period_start: NaiveDate) -> Self {
+        Self {
+            period_start,
+            used: 0,
+            pending: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaDecision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_secs: u64,
+}
+
+const SHARDS: usize = 16;
+
+type Counters = HashMap<String, Counter>;
+
+/// In-memory quota counters, split across independently locked shards.
+pub struct QuotaTracker {
+    shards: Vec<Mutex<Counters>>,
+    hasher: RandomState,
+}
+
+impl QuotaTracker {
+    pub fn new(usage: Vec<DbQuotaUsage>) -> Self {
+        let tracker = Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+        };
+        tracker.sync(usage);
+        tracker
+    }
+
+    fn shard_index(&self, subject: &str) -> usize {
+        self.hasher.hash_one(subject) as usize % SHARDS
+    }
+
+    /// Counts a request against every quota in `quotas` if none of them is
+    /// exhausted, and returns the decision of the most restrictive one.
+    pub fn check(&self, quotas: &[(String, Quota)], now: DateTime<Utc>) -> Option<QuotaDecision> {
+        let (_, first) = quotas.first()?;
+        let reset_secs = |quota: &Quota| {
+            (quota.period.resets_at(quota.period.start(now)) - now)
+                .num_seconds()
+                .max(0) as u64
+        };
+
+        // Shards are locked in index order so concurrent checks spanning the
+        // same shards cannot deadlock.
+        let indices: BTreeSet<usize> = quotas
+            .iter()
+            .map(|(subject, _)| self.shard_index(subject))
+            .collect();
+        let mut guards: BTreeMap<usize, MutexGuard<'_, Counters>> = BTreeMap::new();
+        for index in indices {
+            let Ok(locked) = self.shards[index].lock() else {
+                return Some(QuotaDecision {
+                    allowed: false,
+                    limit: first.limit,
+                    remaining: 0,
+                    reset_secs: reset_secs(first),
+                });
+            };
+            guards.insert(index, locked);
+        }
+
+        let mut usage = Vec::with_capacity(quotas.len());
+        for (subject, quota) in quotas {
+            let Some(counters) = guards.get_mut(&self.shard_index(subject)) else {
+                continue;
+            };
+            let period_start = quota.period.start(now);
+            let counter = counters
+                .entry(subject.clone())
+                .or_insert_with(|| Counter::new(period_start));
+            if counter.period_start != period_start {
+                *counter = Counter::new(period_start);
+            }
+            usage.push(counter.used + counter.pending);
+        }
+
+        let allowed = quotas
+            .iter()
+            .zip(&usage)
+            .all(|((_, quota), used)| *used < quota.limit);
+        if allowed {
+            for (subject, _) in quotas {
+                if let Some(counter) = guards
+                    .get_mut(&self.shard_index(subject))
+                    .and_then(|counters| counters.get_mut(subject))
+                {
+                    counter.pending += 1;
+                }
+            }
+        }
+
+        quotas
+            .iter()
+            .zip(usage)
+            .map(|((_, quota), used)| QuotaDecision {
+                allowed,
+                limit: quota.limit,
+                remaining: quota.limit.saturating_sub(used + allowed as u64),
+                reset_secs: reset_secs(quota),
+            })
+            .min_by_key(|decision| decision.remaining)
+    }
+
+    fn take_pending(&self) -> Vec<(String, NaiveDate, u64)> {
+        let mut pending = Vec::new();
+        for shard in &self.shards {
+            let Ok(mut counters) = shard.lock() else {
+                continue;
+            };
+            pending.extend(
+                counters
+                    .iter_mut()
+                    .filter(|(_, counter)| counter.pending > 0)
+                    .map(|(subject, counter)| {
+                        let pending = std::mem::take(&mut counter.pending);
+                        counter.used += pending;
+                        (subject.clone(), counter.period_start, pending)
+                    }),
+            );
+        }
+        pending
+    }
+
+    fn restore_pending(&self, subject: &str, period_start: NaiveDate, pending: u64) {
+        if let Ok(mut counters) = self.shards[self.shard_index(subject)].lock()
+            && let Some(counter) = counters.get_mut(subject)
+            && counter.period_start == period_start
+        {
+            counter.used = counter.used.saturating_sub(pending);
+            counter.pending += pending;
+        }
+    }
+
+    fn sync(&self, usage: Vec<DbQuotaUsage>) {
+        let mut usage: HashMap<String, DbQuotaUsage> = usage
+            .into_iter()
+            .map(|usage| (usage.subject.clone(), usage))
+            .collect();
+
+        for shard in &self.shards {
+            let Ok(mut counters) = shard.lock() else {
+                continue;
+            };
+            for (subject, counter) in counters.iter_mut() {
+                counter.used = usage
+                    .remove(subject)
+                    .filter(|usage| usage.period_start == counter.period_start.to_string())
+                    .map(|usage| usage.used.max(0) as u64)
+                    .unwrap_or_default();
+            }
+        }
+
+        for (subject, usage) in usage {
+            let Ok(period_start) = usage.period_start.parse() else {
+                continue;
+            };
+            let Ok(mut counters) = self.shards[self.shard_index(&subject)].lock() else {
+                continue;
+            };
+            let mut counter = Counter::new(period_start);
+            counter.used = usage.used.max(0) as u64;
+            counters.insert(subject, counter);
+        }
+    }
+}
+
+pub struct QuotaFlush {
+    tracker: Arc<QuotaTracker>,
+    database: Arc<Database>,
+    interval: Duration,
+}
+
+impl QuotaFlush {
+    pub fn new(tracker: Arc<QuotaTracker>, database: Arc<Database>, interval: Duration) -> Self {
+        Self {
+            tracker,
+            database,
+            interval,
+        }
+    }
+
+    async fn flush(&self) -> Result<(), sqlx::Error> {
+        let pending = self.tracker.take_pending();
+        let mut result = Ok(());
+        for (subject, period_start, count) in pending {
+            if result.is_ok() {
+                result = self
+                    .database
+                    .add_quota_usage(&subject, &period_start.to_string(), count as i64)
+                    .await;
+                if result.is_ok() {
+                    continue;
+                }
+            }
+            self.tracker.restore_pending(&subject, period_start, count);
+        }
+        result?;
+
+        self.tracker.sync(self.database.get_quota_usage().await?);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BackgroundService for QuotaFlush {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => {
+                    if let Err(e) = self.flush().await {
+                        tracing::error!("Failed to flush quota usage on shutdown: {}", e);
+                    }
+                    return;
+                }
+                _ = interval.tick() => {
+                    if let Err(e) = self.flush().await {
+                        tracing::warn!("Failed to flush quota usage: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use cirith_shared::quota::QuotaPeriod;
+
+    fn quota(limit: u64) -> Quota {
+        Quota {
+            limit,
+            period: QuotaPeriod::Daily,
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 23, 0, 0).unwrap()
+    }
+
+    fn check(
+        tracker: &QuotaTracker,
+        subject: &str,
+        quota: Quota,
+        now: DateTime<Utc>,
+    ) -> QuotaDecision {
+        tracker.check(&[(subject.to_string(), quota)], now).unwrap()
+    }
+
+    #[test]
+    fn test_quota_is_exhausted() {
+        let tracker = QuotaTracker::new(vec![DbQuotaUsage {
+            subject: String::from("consumer:partner"),
+            period_start: String::from("2024-05-01"),
+            used: 1,
+        }]);
+
+        let decision = check(&tracker, "consumer:partner", quota(2), now());
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.reset_secs, 3600);
+
+        assert!(!check(&tracker, "consumer:partner", quota(2), now()).allowed);
+    }
+
+    #[test]
+    fn test_new_period_starts_from_zero() {
+        let tracker = QuotaTracker::new(vec![DbQuotaUsage {
+            subject: String::from("consumer:partner"),
+            period_start: String::from("2024-04-30"),
+            used: 2,
+        }]);
+
+        assert!(check(&tracker, "consumer:partner", quota(2), now()).allowed);
+    }
+
+    #[test]
+    fn test_key_and_consumer_quotas_are_both_enforced() {
+        let tracker = QuotaTracker::new(Vec::new());
+        let quotas = |key: &str, key_limit: u64| {
+            vec![
+                (format!("key:{}", key), quota(key_limit)),
+                (String::from("consumer:partner"), quota(3)),
+            ]
+        };
+
+        let decision = tracker.check(&quotas("first", 10), now()).unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.limit, 3);
+        assert_eq!(decision.remaining, 2);
+
+        assert!(tracker.check(&quotas("second", 1), now()).unwrap().allowed);
+        let decision = tracker.check(&quotas("second", 1), now()).unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(decision.limit, 1);
+
+        assert!(tracker.check(&quotas("first", 10), now()).unwrap().allowed);
+        assert!(!tracker.check(&quotas("first", 10), now()).unwrap().allowed);
+        assert_eq!(check(&tracker, "key:first", quota(10), now()).remaining, 7);
+    }
+
+    #[tokio::test]
+    async fn test_flush_persists_and_syncs_resets() {
+        let path = std::env::temp_dir().join(format!("cirith-quota-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let database = Database::new(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        let tracker = Arc::new(QuotaTracker::new(Vec::new()));
+        let flush = QuotaFlush::new(tracker.clone(), Arc::new(database), Duration::from_secs(10));
+
+        let today = QuotaPeriod::Daily.start(Utc::now());
+        check(&tracker, "key:partner", quota(10), Utc::now());
+        check(&tracker, "key:partner", quota(10), Utc::now());
+        flush.flush().await.unwrap();
+        check(&tracker, "key:partner", quota(10), Utc::now());
+        flush.flush().await.unwrap();
+
+        let usage = flush
+            .database
+            .get_subject_quota_usage("key:partner")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(usage.used, 3);
+        assert_eq!(usage.period_start, today.to_string());
+
+        flush
+            .database
+            .reset_quota_usage("key:partner")
+            .await
+            .unwrap();
+        flush.flush().await.unwrap();
+        let decision = check(&tracker, "key:partner", quota(10), Utc::now());
+        assert_eq!(decision.remaining, 9);
+    }
+}
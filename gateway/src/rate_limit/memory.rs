@@ -0,0 +1,237 @@
+use async_trait::async_trait;
+use lru::LruCache;
+use pingora::server::ShutdownWatch;
+use pingora::services::background::BackgroundService;
+use std::hash::{BuildHasher, RandomState};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+// imports
+use crate::rate_limit::{RateLimitDecision, RateLimitStore, StoreError};
+use cirith_shared::config::RateLimit;
+
+const SHARDS: usize = 64;
+const EVICTION_INTERVAL: Duration = Duration::from_secs(30);
+
+struct Window {
+    started_at: Instant,
+    length: Duration,
+    previous: u64,
+    current: u64,
+}
+
+impl Window {
+    fn new(now: Instant, length: Duration) -> Self {
+        Self {
+            started_at: now,
+            length,
+            previous: 0,
+            current: 0,
+        }
+    }
+
+    fn advance(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.started_at);
+        if elapsed >= self.length * 2 {
+            *self = Self::new(now, self.length);
+        } else if elapsed >= self.length {
+            self.previous = self.current;
+            self.current = 0;
+            self.started_at += self.length;
+        }
+    }
+
+    fn estimate(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.started_at).as_secs_f64();
+        let remaining = 1.0 - (elapsed / self.length.as_secs_f64()).min(1.0);
+        self.previous as f64 * remaining + self.current as f64
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        now.duration_since(self.started_at) >= self.length * 2
+    }
+}
+
+/// Sliding windows keyed by client, split across shards. Each shard is an
+/// LRU cache, so a full shard drops its least recently checked key.
+pub struct MemoryStore {
+    shards: Vec<Mutex<LruCache<String, Window>>>,
+    hasher: RandomState,
+}
+
+impl MemoryStore {
+    pub fn new(max_keys: usize) -> MemoryStore {
+        let max_keys_per_shard =
+            NonZeroUsize::new(max_keys.div_ceil(SHARDS)).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            shards: (0..SHARDS)
+                .map(|_| Mutex::new(LruCache::new(max_keys_per_shard)))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub fn check(&self, key: &str, limit: RateLimit) -> RateLimitDecision {
+        let shard = &self.shards[self.hasher.hash_one(key) as usize % SHARDS];
+        let mut windows = match shard.lock() {
+            Ok(guard) => guard,
+            Err(_) => return RateLimitDecision::unavailable(limit, false),
+        };
+
+        let now = Instant::now();
+        let length = Duration::from_secs(limit.window_secs);
+        if !windows.contains(key) {
+            windows.push(key.to_string(), Window::new(now, length));
+        }
+        let Some(window) = windows.get_mut(key) else {
+            return RateLimitDecision::unavailable(limit, false);
+        };
+        if window.length != length {
+            *window = Window::new(now, length);
+        }
+        window.advance(now);
+
+        let allowed = window.estimate(now) < limit.max_requests as f64;
+        if allowed {
+            window.current += 1;
+        }
+
+        RateLimitDecision::sliding_window(
+            limit,
+            allowed,
+            window.previous,
+            window.current,
+            now.duration_since(window.started_at),
+        )
+    }
+
+    pub fn evict_idle(&self) -> usize {
+        let now = Instant::now();
+        self.shards
+            .iter()
+            .filter_map(|shard| shard.lock().ok())
+            .map(|mut windows| {
+                let idle: Vec<String> = windows
+                    .iter()
+                    .filter(|(_, window)| window.is_idle(now))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in &idle {
+                    windows.pop(key);
+                }
+                idle.len()
+            })
+            .sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .filter_map(|shard| shard.lock().ok())
+            .map(|windows| windows.len())
+            .sum()
+    }
+}
+
+pub struct RateLimitEviction {
+    store: Arc<MemoryStore>,
+}
+
+impl RateLimitEviction {
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn check(&self, key: &str, limit: RateLimit) -> Result<RateLimitDecision, StoreError> {
+        Ok(MemoryStore::check(self, key, limit))
+    }
+}
+
+#[async_trait]
+impl BackgroundService for RateLimitEviction {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => return,
+                _ = interval.tick() => {
+                    let evicted = self.store.evict_idle();
+                    if evicted > 0 {
+                        tracing::debug!(evicted, remaining = self.store.len(), "Evicted idle rate limit keys");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(max_requests: u64) -> RateLimit {
+        RateLimit {
+            max_requests,
+            window_secs: 60,
+        }
+    }
+
+    #[test]
+    fn test_keys_are_limited_independently() {
+        let store = MemoryStore::new(1_000);
+        assert!(store.check("ip:203.0.113.9", limit(1)).allowed);
+        assert!(!store.check("ip:203.0.113.9", limit(1)).allowed);
+        assert!(store.check("key:partner", limit(1)).allowed);
+    }
+
+    #[test]
+    fn test_limit_is_applied_per_check() {
+        let store = MemoryStore::new(1_000);
+        assert!(store.check("key:partner", limit(1)).allowed);
+        assert!(!store.check("key:partner", limit(1)).allowed);
+        assert!(store.check("key:partner", limit(3)).allowed);
+    }
+
+    #[test]
+    fn test_previous_window_is_weighted() {
+        let now = Instant::now();
+        let length = Duration::from_secs(60);
+        let mut window = Window::new(now, length);
+        window.current = 10;
+
+        window.advance(now + Duration::from_secs(75));
+        assert_eq!(window.previous, 10);
+        assert_eq!(window.current, 0);
+        assert_eq!(window.estimate(now + Duration::from_secs(75)), 7.5);
+
+        window.advance(now + Duration::from_secs(200));
+        assert_eq!(window.previous, 0);
+        assert!(window.is_idle(now + Duration::from_secs(320)));
+    }
+
+    #[test]
+    fn test_key_count_is_bounded() {
+        let store = MemoryStore::new(SHARDS);
+        for i in 0..SHARDS * 10 {
+            assert!(store.check(&format!("ip:{}", i), limit(5)).allowed);
+        }
+        assert!(store.len() <= SHARDS);
+    }
+
+    #[test]
+    fn test_full_shard_evicts_least_recently_checked_key() {
+        let store = MemoryStore::new(1);
+        let shard = |key: &str| store.hasher.hash_one(key) as usize % SHARDS;
+        let first = "ip:first";
+        let mut keys = (0..).map(|i| format!("ip:{}", i));
+        let second = keys.find(|key| shard(key) == shard(first)).unwrap();
+
+        assert!(store.check(first, limit(1)).allowed);
+        assert!(!store.check(first, limit(1)).allowed);
+        assert!(store.check(&second, limit(1)).allowed);
+        assert!(store.check(first, limit(1)).allowed);
+    }
+}
@@ -0,0 +1,141 @@
+pub mod memory;
+pub mod policies;
+pub mod redis;
+
+use async_trait::async_trait;
+use prometheus::{IntCounterVec, register_int_counter_vec};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+// imports
+use cirith_shared::config::{FailureMode, RateLimit};
+
+pub type StoreError = Box<dyn std::error::Error + Send + Sync>;
+
+pub static SHADOW_REJECTIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "cirith_rate_limit_shadow_rejections_total",
+        "Requests a shadow rate limit policy would have rejected",
+        &["policy"]
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_secs: u64,
+    pub retry_after_secs: Option<u64>,
+}
+
+impl RateLimitDecision {
+    pub fn sliding_window(
+        limit: RateLimit,
+        allowed: bool,
+        previous: u64,
+        current: u64,
+        elapsed: Duration,
+    ) -> Self {
+        let length = limit.window_secs as f64;
+        let elapsed = elapsed.as_secs_f64().min(length);
+        let max = limit.max_requests as f64;
+        let estimate = previous as f64 * (1.0 - elapsed / length) + current as f64;
+
+        let retry_after_secs = (!allowed).then(|| {
+            let wait = if current < limit.max_requests {
+                length * (1.0 - (max - current as f64) / previous.max(1) as f64) - elapsed
+            } else {
+                length - elapsed + length * (1.0 - max / current as f64)
+            };
+            (wait.ceil() as u64).max(1)
+        });
+
+        Self {
+            allowed,
+            limit: limit.max_requests,
+            remaining: if allowed {
+                (max - estimate).max(0.0).ceil() as u64
+            } else {
+                0
+            },
+            reset_secs: (length - elapsed).ceil() as u64,
+            retry_after_secs,
+        }
+    }
+
+    fn unavailable(limit: RateLimit, allowed: bool) -> Self {
+        Self {
+            allowed,
+            limit: limit.max_requests,
+            remaining: if allowed { limit.max_requests } else { 0 },
+            reset_secs: limit.window_secs,
+            retry_after_secs: (!allowed).then_some(1),
+        }
+    }
+}
+
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn check(&self, key: &str, limit: RateLimit) -> Result<RateLimitDecision, StoreError>;
+}
+
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    failure_mode: FailureMode,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn RateLimitStore>, failure_mode: FailureMode) -> Self {
+        Self {
+            store,
+            failure_mode,
+        }
+    }
+
+    pub async fn check(&self, key: &str, limit: RateLimit) -> RateLimitDecision {
+        match self.store.check(key, limit).await {
+            Ok(decision) => decision,
+            Err(e) => {
+                tracing::warn!(key = %key, mode = ?self.failure_mode, "Rate limit store unavailable: {}", e);
+                RateLimitDecision::unavailable(limit, self.failure_mode == FailureMode::Open)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit() -> RateLimit {
+        RateLimit {
+            max_requests: 10,
+            window_secs: 60,
+        }
+    }
+
+    #[test]
+    fn test_allowed_decision_counts_remaining() {
+        let decision =
+            RateLimitDecision::sliding_window(limit(), true, 4, 3, Duration::from_secs(30));
+        assert_eq!(decision.remaining, 5);
+        assert_eq!(decision.reset_secs, 30);
+        assert_eq!(decision.retry_after_secs, None);
+    }
+
+    #[test]
+    fn test_denied_decision_waits_for_previous_window_to_slide() {
+        let decision =
+            RateLimitDecision::sliding_window(limit(), false, 10, 5, Duration::from_secs(15));
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.retry_after_secs, Some(15));
+    }
+
+    #[test]
+    fn test_denied_decision_waits_for_next_window() {
+        let decision =
+            RateLimitDecision::sliding_window(limit(), false, 0, 10, Duration::from_secs(45));
+        assert_eq!(decision.retry_after_secs, Some(15));
+    }
+}
@@ -0,0 +1,204 @@
+use async_trait::async_trait;
+use pingora::server::ShutdownWatch;
+use pingora::services::background::BackgroundService;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+// imports
+use cirith_shared::config::{RateLimit, RateLimitPolicy};
+use cirith_shared::storage::{Database, DbConsumer, DbRoute};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitPolicies {
+    global: RateLimitPolicy,
+    routes: HashMap<i64, RateLimitPolicy>,
+    consumers: HashMap<i64, RateLimit>,
+}
+
+impl RateLimitPolicies {
+    pub fn new(global: RateLimitPolicy, routes: &[DbRoute], consumers: &[DbConsumer]) -> Self {
+        Self {
+            global,
+            routes: routes
+                .iter()
+                .filter_map(|route| Some((route.id, route.rate_limit.as_ref()?.0.clone())))
+                .collect(),
+            consumers: consumers
+                .iter()
+                .filter_map(|consumer| Some((consumer.id, consumer.rate_limit.as_ref()?.0)))
+                .collect(),
+        }
+    }
+
+    pub fn global(&self) -> &RateLimitPolicy {
+        &self.global
+    }
+
+    pub fn route(&self, id: i64) -> Option<&RateLimitPolicy> {
+        self.routes.get(&id)
+    }
+
+    pub fn consumer(&self, id: i64) -> Option<RateLimit> {
+        self.consumers.get(&id).copied()
+    }
+}
+
+pub struct PolicyStore {
+    policies: RwLock<Arc<RateLimitPolicies>>,
+}
+
+impl PolicyStore {
+    pub fn new(policies: RateLimitPolicies) -> Self {
+        Self {
+            policies: RwLock::new(Arc::new(policies)),
+        }
+    }
+
+    pub fn get(&self) -> Arc<RateLimitPolicies> {
+        match self.policies.read() {
+            Ok(policies) => policies.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn set(&self, policies: RateLimitPolicies) -> bool {
+        let Ok(mut current) = self.policies.write() else {
+            return false;
+        };
+        if **current == policies {
+            return false;
+        }
+        *current = Arc::new(policies);
+        true
+    }
+}
+
+pub struct PolicyReload {
+    store: Arc<PolicyStore>,
+    database: Arc<Database>,
+    default: RateLimitPolicy,
+    interval: Duration,
+}
+
+impl PolicyReload {
+    pub fn new(
+        store: Arc<PolicyStore>,
+        database: Arc<Database>,
+        default: RateLimitPolicy,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            store,
+            database,
+            default,
+            interval,
+        }
+    }
+
+    async fn reload(&self) -> Result<bool, sqlx::Error> {
+        let global = self.database.get_global_rate_limit().await?;
+        let routes = self.database.get_routes().await?;
+        let consumers = self.database.get_consumers().await?;
+
+        Ok(self.store.set(RateLimitPolicies::new(
+            global.unwrap_or_else(|| self.default.clone()),
+            &routes,
+            &consumers,
+        )))
+    }
+}
+
+#[async_trait]
+impl BackgroundService for PolicyReload {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => return,
+                _ = interval.tick() => match self.reload().await {
+                    Ok(true) => tracing::info!("Reloaded rate limit policies"),
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Failed to reload rate limit policies: {}", e),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cirith_shared::config::RateLimitKey;
+    use cirith_shared::storage::NewRoute;
+    use std::collections::HashMap;
+
+    fn policy(max_requests: u64) -> RateLimitPolicy {
+        RateLimitPolicy {
+            max_requests,
+            window_secs: 60,
+            key_by: RateLimitKey::Ip,
+            shadow: false,
+            exempt: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_applies_database_policies() {
+        let path = std::env::temp_dir().join(format!("cirith-policies-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let database = Database::new(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        let route = database
+            .add_route(&NewRoute {
+                path: String::from("/search"),
+                upstream: String::from("https://httpbin.org"),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let consumer = database
+            .add_consumer("partner", &HashMap::new(), &[], None, None)
+            .await
+            .unwrap();
+
+        let store = Arc::new(PolicyStore::new(RateLimitPolicies::new(
+            policy(100),
+            &[],
+            &[],
+        )));
+        let reload = PolicyReload::new(
+            store.clone(),
+            Arc::new(database),
+            policy(100),
+            Duration::from_secs(5),
+        );
+        assert!(!reload.reload().await.unwrap());
+
+        reload
+            .database
+            .set_global_rate_limit(&policy(50))
+            .await
+            .unwrap();
+        reload
+            .database
+            .update_route_rate_limit("/search", Some(&policy(10)))
+            .await
+            .unwrap();
+        reload
+            .database
+            .update_consumer_rate_limit("partner", Some(&policy(1000).limit()))
+            .await
+            .unwrap();
+        assert!(reload.reload().await.unwrap());
+
+        let policies = store.get();
+        assert_eq!(policies.global().max_requests, 50);
+        assert_eq!(policies.route(route.id).unwrap().max_requests, 10);
+        assert_eq!(policies.consumer(consumer.id).unwrap().max_requests, 1000);
+
+        reload.database.delete_global_rate_limit().await.unwrap();
+        assert!(reload.reload().await.unwrap());
+        assert_eq!(store.get().global().max_requests, 100);
+    }
+}
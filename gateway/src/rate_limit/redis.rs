@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{Client, Script};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+// imports
+use crate::rate_limit::{RateLimitDecision, RateLimitStore, StoreError};
+use cirith_shared::config::{RateLimit, RedisConfig};
+
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local max_requests = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local index = math.floor(now / window)
+
+local state = redis.call('HMGET', KEYS[1], 'index', 'previous', 'current')
+local stored = tonumber(state[1]) or index
+local previous = tonumber(state[2]) or 0
+local current = tonumber(state[3]) or 0
+if stored == index - 1 then
+    previous = current
+    current = 0
+elseif stored ~= index then
+    previous = 0
+    current = 0
+end
+
+local weight = 1 - (now % window) / window
+local allowed = previous * weight + current < max_requests
+if allowed then
+    current = current + 1
+end
+
+redis.call('HSET', KEYS[1], 'index', index, 'previous', previous, 'current', current)
+redis.call('PEXPIRE', KEYS[1], window * 2)
+return {allowed and 1 or 0, previous, current, now % window}
+"#;
+
+pub struct RedisStore {
+    client: Client,
+    connection: OnceCell<ConnectionManager>,
+    script: Script,
+    key_prefix: String,
+    timeout: Duration,
+}
+
+impl RedisStore {
+    pub fn new(config: &RedisConfig) -> redis::RedisResult<Self> {
+        Ok(Self {
+            client: Client::open(config.url.as_str())?,
+            connection: OnceCell::new(),
+            script: Script::new(SLIDING_WINDOW_SCRIPT),
+            key_prefix: config.key_prefix.clone(),
+            timeout: Duration::from_millis(config.timeout_ms),
+        })
+    }
+
+    async fn connection(&self) -> redis::RedisResult<ConnectionManager> {
+        let config = ConnectionManagerConfig::new()
+            .set_connection_timeout(self.timeout)
+            .set_response_timeout(self.timeout)
+            .set_number_of_retries(1);
+        self.connection
+            .get_or_try_init(|| self.client.get_connection_manager_with_config(config))
+            .await
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    async fn check(&self, key: &str, limit: RateLimit) -> Result<RateLimitDecision, StoreError> {
+        let check = async {
+            let mut connection = self.connection().await?;
+            let (allowed, previous, current, elapsed_ms): (i64, u64, u64, u64) = self
+                .script
+                .key(format!("{}{}", self.key_prefix, key))
+                .arg(limit.max_requests)
+                .arg(limit.window_secs * 1000)
+                .invoke_async(&mut connection)
+                .await?;
+            Ok::<_, redis::RedisError>(RateLimitDecision::sliding_window(
+                limit,
+                allowed == 1,
+                previous,
+                current,
+                Duration::from_millis(elapsed_ms),
+            ))
+        };
+
+        Ok(tokio::time::timeout(self.timeout * 2, check).await??)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rate_limit::RateLimiter;
+    use cirith_shared::config::FailureMode;
+    use std::sync::Arc;
+
+    fn config(url: &str) -> RedisConfig {
+        RedisConfig {
+            url: url.to_string(),
+            failure_mode: FailureMode::Open,
+            key_prefix: format!("cirith-test:{}:", std::process::id()),
+            timeout_ms: 200,
+        }
+    }
+
+    fn limit(max_requests: u64) -> RateLimit {
+        RateLimit {
+            max_requests,
+            window_secs: 60,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_redis_uses_failure_mode() {
+        let store = || Arc::new(RedisStore::new(&config("redis://127.0.0.1:1")).unwrap());
+
+        let open = RateLimiter::new(store(), FailureMode::Open);
+        assert!(open.check("ip:203.0.113.9", limit(1)).await.allowed);
+
+        let closed = RateLimiter::new(store(), FailureMode::Closed);
+        let decision = closed.check("ip:203.0.113.9", limit(1)).await;
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_secs, Some(1));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Redis server in REDIS_URL"]
+    async fn test_redis_counts_are_shared() {
+        let url = std::env::var("REDIS_URL").expect("REDIS_URL must be set");
+        let first = RedisStore::new(&config(&url)).unwrap();
+        let second = RedisStore::new(&config(&url)).unwrap();
+
+        let decision = first.check("key:partner", limit(2)).await.unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 1);
+        assert!(second.check("key:partner", limit(2)).await.unwrap().allowed);
+
+        let decision = first.check("key:partner", limit(2)).await.unwrap();
+        assert!(!decision.allowed);
+        assert!(decision.retry_after_secs.is_some());
+        assert!(second.check("key:other", limit(2)).await.unwrap().allowed);
+    }
+}
@@ -0,0 +1,183 @@
+use pingora::Result;
+use pingora::http::{RequestHeader, ResponseHeader};
+// imports
+use cirith_shared::transform::{HeaderTransforms, Variable, render};
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+const SECURITY_HEADERS: &[(&str, &str)] = &[
+    (
+        "Strict-Transport-Security",
+        "max-age=31536000; includeSubDomains",
+    ),
+    ("X-Content-Type-Options", "nosniff"),
+    ("X-Frame-Options", "DENY"),
+    ("Referrer-Policy", "strict-origin-when-cross-origin"),
+];
+
+pub trait Headers {
+    fn values(&self, name: &str) -> Vec<Vec<u8>>;
+    fn remove(&mut self, name: &str);
+    fn insert(&mut self, name: String, value: Vec<u8>) -> Result<()>;
+    fn append(&mut self, name: String, value: Vec<u8>) -> Result<()>;
+}
+
+macro_rules! impl_headers {
+    ($type:ty) => {
+        impl Headers for $type {
+            fn values(&self, name: &str) -> Vec<Vec<u8>> {
+                self.headers
+                    .get_all(name)
+                    .iter()
+                    .map(|v| v.as_bytes().to_vec())
+                    .collect()
+            }
+
+            fn remove(&mut self, name: &str) {
+                self.remove_header(name);
+            }
+
+            fn insert(&mut self, name: String, value: Vec<u8>) -> Result<()> {
+                self.insert_header(name, value)
+            }
+
+            fn append(&mut self, name: String, value: Vec<u8>) -> Result<()> {
+                self.append_header(name, value).map(|_| ())
+            }
+        }
+    };
+}
+
+impl_headers!(RequestHeader);
+impl_headers!(ResponseHeader);
+
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+pub fn apply<'a>(
+    transforms: &'a HeaderTransforms,
+    headers: &mut impl Headers,
+    resolve: impl Fn(Variable<'a>) -> Option<String>,
+) {
+    for name in &transforms.remove {
+        headers.remove(name);
+    }
+
+    for (from, to) in &transforms.rename {
+        let values = headers.values(from);
+        headers.remove(from);
+        for value in values {
+            if headers.append(to.clone(), value).is_err() {
+                tracing::warn!(header = %to, "Skipping invalid renamed header");
+            }
+        }
+    }
+
+    for (name, template) in &transforms.set {
+        let Some(value) = render(template, &resolve) else {
+            tracing::debug!(header = %name, "Skipping header with unresolved variables");
+            continue;
+        };
+        if headers.insert(name.clone(), value.into_bytes()).is_err() {
+            tracing::warn!(header = %name, "Skipping invalid header value");
+        }
+    }
+
+    for (name, template) in &transforms.add {
+        let Some(value) = render(template, &resolve) else {
+            tracing::debug!(header = %name, "Skipping header with unresolved variables");
+            continue;
+        };
+        if headers.append(name.clone(), value.into_bytes()).is_err() {
+            tracing::warn!(header = %name, "Skipping invalid header value");
+        }
+    }
+}
+
+pub fn apply_security_headers(response: &mut ResponseHeader) -> Result<()> {
+    for (name, value) in SECURITY_HEADERS {
+        if !response.headers.contains_key(*name) {
+            response.insert_header(*name, *value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_apply_request_transforms() {
+        let mut request = RequestHeader::build("GET", b"/users/42", None).unwrap();
+        request.insert_header("X-Internal", "secret").unwrap();
+        request.insert_header("X-Legacy-Token", "abc").unwrap();
+        request.insert_header("X-Tenant", "client").unwrap();
+        request.insert_header("X-Trace", "one").unwrap();
+
+        let transforms = HeaderTransforms {
+            remove: vec![String::from("X-Internal")],
+            rename: BTreeMap::from([(String::from("X-Legacy-Token"), String::from("X-Token"))]),
+            set: BTreeMap::from([
+                (String::from("X-Tenant"), String::from("${path.1}")),
+                (String::from("X-Broken"), String::from("${header.x-evil}")),
+                (String::from("X-Missing"), String::from("id-${path.2}")),
+            ]),
+            add: BTreeMap::from([(String::from("X-Trace"), String::from("${request_id}"))]),
+        };
+        apply(&transforms, &mut request, |variable| match variable {
+            Variable::PathSegment(1) => Some(String::from("42")),
+            Variable::RequestId => Some(String::from("req-1")),
+            Variable::Header(_) => Some(String::from("bad\nvalue")),
+            _ => None,
+        });
+
+        let header = |name: &str| {
+            request
+                .headers
+                .get_all(name)
+                .iter()
+                .map(|v| v.to_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert!(header("X-Internal").is_empty());
+        assert!(header("X-Legacy-Token").is_empty());
+        assert_eq!(header("X-Token"), vec!["abc"]);
+        assert_eq!(header("X-Tenant"), vec!["42"]);
+        assert!(header("X-Broken").is_empty());
+        assert!(header("X-Missing").is_empty());
+        assert_eq!(header("X-Trace"), vec!["one", "req-1"]);
+    }
+
+    #[test]
+    fn test_security_headers_keep_upstream_values() {
+        let mut response = ResponseHeader::build(200, None).unwrap();
+        response
+            .insert_header("X-Frame-Options", "SAMEORIGIN")
+            .unwrap();
+        response.insert_header("Server", "nginx").unwrap();
+        apply_security_headers(&mut response).unwrap();
+
+        let transforms = HeaderTransforms {
+            remove: vec![String::from("Server"), String::from("Referrer-Policy")],
+            ..Default::default()
+        };
+        apply(&transforms, &mut response, |_| None);
+
+        assert_eq!(response.headers["X-Frame-Options"], "SAMEORIGIN");
+        assert_eq!(response.headers["X-Content-Type-Options"], "nosniff");
+        assert!(response.headers.contains_key("Strict-Transport-Security"));
+        assert!(!response.headers.contains_key("Referrer-Policy"));
+        assert!(!response.headers.contains_key("Server"));
+    }
+
+    #[test]
+    fn test_request_id_validation() {
+        assert!(is_valid_request_id("0f3c-11ee"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has space"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+}
@@ -0,0 +1,175 @@
+use argon2::password_hash::{SaltString, rand_core::OsRng};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const HMAC_SHA256_PREFIX: &str = "hmac-sha256$";
+const ARGON2_PREFIX: &str = "$argon2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashScheme {
+    LegacySha256,
+    HmacSha256,
+    Argon2,
+}
+
+impl HashScheme {
+    pub fn detect(stored: &str) -> Option<Self> {
+        if stored.starts_with(HMAC_SHA256_PREFIX) {
+            Some(Self::HmacSha256)
+        } else if stored.starts_with(ARGON2_PREFIX) {
+            Some(Self::Argon2)
+        } else if stored.len() == 64 && stored.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Some(Self::LegacySha256)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    Invalid,
+    Valid,
+    NeedsRehash,
+}
+
+impl Verification {
+    pub fn is_valid(self) -> bool {
+        self != Self::Invalid
+    }
+}
+
+pub fn hash_api_key(key: &str, pepper: &str) -> String {
+    format!("{}{}", HMAC_SHA256_PREFIX, hmac_sha256(key, pepper))
+}
+
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash password: {}", e))
+}
+
+/// Checks `secret` against a stored hash. HMAC-SHA256 hashes never match
+/// without a pepper; legacy and argon2 hashes don't need one.
+pub fn verify_secret(secret: &str, stored: &str, pepper: Option<&str>) -> Verification {
+    let valid = match HashScheme::detect(stored) {
+        Some(HashScheme::HmacSha256) => pepper.is_some_and(|pepper| {
+            let expected = &stored[HMAC_SHA256_PREFIX.len()..];
+            constant_time_eq(&hmac_sha256(secret, pepper), expected)
+        }),
+        Some(HashScheme::Argon2) => match PasswordHash::new(stored) {
+            Ok(hash) => Argon2::default()
+                .verify_password(secret.as_bytes(), &hash)
+                .is_ok(),
+            Err(_) => false,
+        },
+        Some(HashScheme::LegacySha256) => {
+            if constant_time_eq(&legacy_sha256(secret), &stored.to_ascii_lowercase()) {
+                return Verification::NeedsRehash;
+            }
+            false
+        }
+        None => false,
+    };
+
+    if valid {
+        Verification::Valid
+    } else {
+        Verification::Invalid
+    }
+}
+
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+fn legacy_sha256(key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub(crate) fn hmac_sha256(message: &str, key: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_HASH: &str = "2ceac6f36363c6246a64cca805cd43ca7a01b14eb2fcc532ceec3f60f2f7df1c";
+
+    #[test]
+    fn test_detect_scheme() {
+        assert_eq!(
+            HashScheme::detect(LEGACY_HASH),
+            Some(HashScheme::LegacySha256)
+        );
+        assert_eq!(
+            HashScheme::detect(&hash_api_key("secret", "pepper")),
+            Some(HashScheme::HmacSha256)
+        );
+        assert_eq!(
+            HashScheme::detect(&hash_password("secret").unwrap()),
+            Some(HashScheme::Argon2)
+        );
+        assert_eq!(HashScheme::detect("not-a-hash"), None);
+    }
+
+    #[test]
+    fn test_verify_hmac_api_key() {
+        let stored = hash_api_key("secret", "pepper");
+        assert_eq!(
+            verify_secret("secret", &stored, Some("pepper")),
+            Verification::Valid
+        );
+        assert_eq!(
+            verify_secret("wrong", &stored, Some("pepper")),
+            Verification::Invalid
+        );
+        assert_eq!(
+            verify_secret("secret", &stored, Some("other-pepper")),
+            Verification::Invalid
+        );
+        assert_eq!(
+            verify_secret("secret", &stored, None),
+            Verification::Invalid
+        );
+    }
+
+    #[test]
+    fn test_verify_legacy_hash_needs_rehash() {
+        let stored = legacy_sha256("secret");
+        assert_eq!(
+            verify_secret("secret", &stored, Some("pepper")),
+            Verification::NeedsRehash
+        );
+        assert_eq!(
+            verify_secret("wrong", &stored, Some("pepper")),
+            Verification::Invalid
+        );
+        assert_eq!(
+            verify_secret("secret", &stored, None),
+            Verification::NeedsRehash
+        );
+    }
+
+    #[test]
+    fn test_verify_password() {
+        let stored = hash_password("hunter2").unwrap();
+        assert_eq!(verify_secret("hunter2", &stored, None), Verification::Valid);
+        assert_eq!(
+            verify_secret("hunter3", &stored, None),
+            Verification::Invalid
+        );
+    }
+}
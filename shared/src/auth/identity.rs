@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+// imports
+use crate::auth::{AuthMode, Identity};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentityField {
+    Credential,
+    AuthMode,
+    ConsumerName,
+    ConsumerId,
+    ConsumerMetadata(String),
+    Claim(String),
+}
+
+impl IdentityField {
+    pub fn resolve(&self, identity: &Identity, mode: AuthMode) -> Option<String> {
+        match self {
+            Self::Credential => Some(identity.credential.clone()),
+            Self::AuthMode => serde_json::to_value(mode)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string)),
+            Self::ConsumerName => identity.consumer.as_ref().map(|c| c.name.clone()),
+            Self::ConsumerId => identity.consumer.as_ref().map(|c| c.id.to_string()),
+            Self::ConsumerMetadata(key) => identity
+                .consumer
+                .as_ref()
+                .and_then(|c| c.metadata.get(key).cloned()),
+            Self::Claim(name) => identity.claims.get(name).cloned(),
+        }
+    }
+}
+
+impl FromStr for IdentityField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "credential" => Ok(Self::Credential),
+            "auth_mode" => Ok(Self::AuthMode),
+            "consumer.name" => Ok(Self::ConsumerName),
+            "consumer.id" => Ok(Self::ConsumerId),
+            _ => {
+                if let Some(key) = s.strip_prefix("consumer.metadata.")
+                    && !key.is_empty()
+                {
+                    Ok(Self::ConsumerMetadata(key.to_string()))
+                } else if let Some(name) = s.strip_prefix("claims.")
+                    && !name.is_empty()
+                {
+                    Ok(Self::Claim(name.to_string()))
+                } else {
+                    Err(format!("Unknown identity field: {}", s))
+                }
+            }
+        }
+    }
+}
+
+pub fn default_identity_headers() -> HashMap<String, String> {
+    HashMap::from([
+        (
+            String::from("X-Consumer-Name"),
+            String::from("consumer.name"),
+        ),
+        (String::from("X-Consumer-Id"), String::from("consumer.id")),
+        (
+            String::from("X-Credential-Name"),
+            String::from("credential"),
+        ),
+        (String::from("X-Auth-Mode"), String::from("auth_mode")),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DbConsumer;
+    use sqlx::types::Json;
+
+    fn identity() -> Identity {
+        Identity {
+            credential: String::from("partner-key"),
+            consumer: Some(DbConsumer {
+                id: 7,
+                name: String::from("partner"),
+                metadata: Json(HashMap::from([(
+                    String::from("tier"),
+                    String::from("gold"),
+                )])),
+                tags: Json(vec![]),
+                rate_limit: None,
+                quota: None,
+            }),
+            claims: HashMap::from([(String::from("sub"), String::from("user-1"))]),
+            rate_limit: None,
+            quota: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_identity_fields() {
+        assert_eq!("credential".parse(), Ok(IdentityField::Credential));
+        assert_eq!(
+            "consumer.metadata.tier".parse(),
+            Ok(IdentityField::ConsumerMetadata(String::from("tier")))
+        );
+        assert_eq!(
+            "claims.sub".parse(),
+            Ok(IdentityField::Claim(String::from("sub")))
+        );
+        assert!("claims.".parse::<IdentityField>().is_err());
+        assert!("password".parse::<IdentityField>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_identity_fields() {
+        let identity = identity();
+        let resolve = |field: &str| {
+            field
+                .parse::<IdentityField>()
+                .unwrap()
+                .resolve(&identity, AuthMode::ApiKey)
+        };
+
+        assert_eq!(resolve("credential").as_deref(), Some("partner-key"));
+        assert_eq!(resolve("auth_mode").as_deref(), Some("api-key"));
+        assert_eq!(resolve("consumer.name").as_deref(), Some("partner"));
+        assert_eq!(resolve("consumer.id").as_deref(), Some("7"));
+        assert_eq!(resolve("consumer.metadata.tier").as_deref(), Some("gold"));
+        assert_eq!(resolve("claims.sub").as_deref(), Some("user-1"));
+        assert_eq!(resolve("claims.email"), None);
+    }
+}
@@ -0,0 +1,579 @@
+pub mod hash;
+pub mod identity;
+pub mod secret;
+pub mod signature;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+// imports
+use crate::auth::hash::{HashScheme, Verification, hash_api_key, hash_password, verify_secret};
+use crate::auth::secret::SecretCipher;
+use crate::auth::signature::{
+    CONTENT_SHA256_HEADER, KEY_ID_HEADER, ReplayCache, SIGNATURE_HEADER, SIGNED_HEADERS_HEADER,
+    TIMESTAMP_HEADER, canonical_request,
+};
+use crate::config::{ApiKey, AuthConfig, RateLimit};
+use crate::quota::Quota;
+use crate::storage::{
+    DbApiKey, DbBasicAuthCredential, DbClientCertificate, DbConsumer, DbHmacCredential,
+};
+
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("cirith-dummy-password").unwrap_or_default());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(type_name = "TEXT", rename_all = "kebab-case")]
+pub enum AuthMode {
+    #[default]
+    None,
+    ApiKey,
+    Basic,
+    Hmac,
+    Mtls,
+    #[serde(rename = "oauth2")]
+    #[sqlx(rename = "oauth2")]
+    OAuth2,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Identity {
+    pub credential: String,
+    pub consumer: Option<DbConsumer>,
+    pub claims: HashMap<String, String>,
+    pub rate_limit: Option<RateLimit>,
+    pub quota: Option<Quota>,
+}
+
+#[derive(Debug, Clone)]
+struct HmacCredential {
+    key_id: String,
+    secret: String,
+    consumer_id: Option<i64>,
+}
+
+/// A database-backed API key whose legacy hash was replaced on use and
+/// still has to be written back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMigration {
+    pub name: String,
+    pub legacy_hash: String,
+    pub key_hash: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthValidator {
+    enabled: bool,
+    pepper: Option<String>,
+    max_skew_secs: u64,
+    api_keys: Arc<RwLock<Vec<ApiKey>>>,
+    // Keys at or after this index were loaded from the database.
+    stored_keys_from: usize,
+    migrations: Arc<Mutex<Vec<KeyMigration>>>,
+    basic_credentials: Arc<Vec<DbBasicAuthCredential>>,
+    hmac_credentials: Arc<Vec<HmacCredential>>,
+    client_certificates: Arc<Vec<DbClientCertificate>>,
+    replay_cache: Arc<ReplayCache>,
+    consumers: Arc<Vec<DbConsumer>>,
+}
+
+impl AuthValidator {
+    pub fn new(config: &AuthConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            pepper: config.pepper.clone(),
+            max_skew_secs: config.signature.max_skew_secs,
+            api_keys: Arc::new(RwLock::new(config.api_keys.clone())),
+            stored_keys_from: config.api_keys.len(),
+            migrations: Arc::new(Mutex::new(Vec::new())),
+            basic_credentials: Arc::new(Vec::new()),
+            hmac_credentials: Arc::new(Vec::new()),
+            client_certificates: Arc::new(Vec::new()),
+            replay_cache: Arc::new(ReplayCache::new(config.signature.replay_cache_size)),
+            consumers: Arc::new(Vec::new()),
+        }
+    }
+
+    pub fn with_database_keys(mut self, consumers: Vec<DbConsumer>, keys: Vec<DbApiKey>) -> Self {
+        if let Ok(mut api_keys) = self.api_keys.write() {
+            self.stored_keys_from = api_keys.len();
+            api_keys.extend(keys.into_iter().map(|key| ApiKey {
+                consumer: key.consumer_id.and_then(|id| {
+                    consumers
+                        .iter()
+                        .find(|consumer| consumer.id == id)
+                        .map(|consumer| consumer.name.clone())
+                }),
+                name: key.name,
+                key_hash: key.key_hash,
+                rate_limit: key.rate_limit.map(|limit| limit.0),
+                quota: key.quota.map(|quota| quota.0),
+            }));
+        }
+
+        self.consumers = Arc::new(consumers);
+        self
+    }
+
+    pub fn with_basic_credentials(mut self, credentials: Vec<DbBasicAuthCredential>) -> Self {
+        self.basic_credentials = Arc::new(credentials);
+        self
+    }
+
+    pub fn with_hmac_credentials(
+        mut self,
+        credentials: Vec<DbHmacCredential>,
+        cipher: Option<&SecretCipher>,
+    ) -> Self {
+        let Some(cipher) = cipher else {
+            if !credentials.is_empty() {
+                tracing::warn!("HMAC credentials ignored, auth.encryption_key is not configured");
+            }
+            return self;
+        };
+
+        let credentials = credentials
+            .into_iter()
+            .filter_map(|credential| match cipher.decrypt(&credential.secret_encrypted) {
+                Ok(secret) => Some(HmacCredential {
+                    key_id: credential.key_id,
+                    secret,
+                    consumer_id: credential.consumer_id,
+                }),
+                Err(e) => {
+                    tracing::warn!(key_id = %credential.key_id, "Skipping HMAC credential: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        self.hmac_credentials = Arc::new(credentials);
+        self
+    }
+
+    pub fn with_client_certificates(mut self, certificates: Vec<DbClientCertificate>) -> Self {
+        self.client_certificates = Arc::new(certificates);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn default_mode(&self) -> AuthMode {
+        if self.enabled {
+            AuthMode::ApiKey
+        } else {
+            AuthMode::None
+        }
+    }
+
+    /// Hashes a new API key, or returns `None` when no pepper is configured.
+    pub fn hash_key(&self, key: &str) -> Option<String> {
+        self.pepper
+            .as_deref()
+            .map(|pepper| hash_api_key(key, pepper))
+    }
+
+    pub fn validate(&self, key: &str) -> Option<Identity> {
+        let (index, identity, verification) = {
+            let api_keys = self.api_keys.read().ok()?;
+
+            api_keys.iter().enumerate().find_map(|(index, api_key)| {
+                match verify_secret(key, &api_key.key_hash, self.pepper.as_deref()) {
+                    Verification::Invalid => None,
+                    verification => Some((index, self.identity(api_key), verification)),
+                }
+            })?
+        };
+
+        if verification == Verification::NeedsRehash {
+            self.migrate(index, key);
+        }
+
+        Some(identity)
+    }
+
+    pub fn validate_basic(&self, authorization: &str) -> Option<Identity> {
+        let (scheme, encoded) = authorization.trim_start().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+        let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+
+        let Some(credential) = self
+            .basic_credentials
+            .iter()
+            .find(|credential| credential.username == username)
+        else {
+            verify_secret(password, &DUMMY_PASSWORD_HASH, self.pepper.as_deref());
+            return None;
+        };
+
+        if !verify_secret(password, &credential.password_hash, self.pepper.as_deref()).is_valid() {
+            return None;
+        }
+
+        Some(Identity {
+            credential: credential.username.clone(),
+            consumer: self.consumer_by_id(credential.consumer_id),
+            ..Default::default()
+        })
+    }
+
+    pub fn validate_signature<'a>(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        header: impl Fn(&str) -> Option<&'a str>,
+        now: u64,
+    ) -> Result<Identity, &'static str> {
+        let key_id = header(KEY_ID_HEADER).ok_or("Missing signature key id")?;
+        let signature = header(SIGNATURE_HEADER).ok_or("Missing signature")?;
+        let timestamp = header(TIMESTAMP_HEADER).ok_or("Missing signature timestamp")?;
+        let content_sha256 = header(CONTENT_SHA256_HEADER).ok_or("Missing content digest")?;
+
+        let signed_at: u64 = timestamp
+            .parse()
+            .map_err(|_| "Invalid signature timestamp")?;
+        if now.abs_diff(signed_at) > self.max_skew_secs {
+            return Err("Stale signature timestamp");
+        }
+
+        let signed_headers = header(SIGNED_HEADERS_HEADER)
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| Ok((name, header(name).ok_or("Missing signed header")?)))
+            .collect::<Result<Vec<_>, &'static str>>()?;
+
+        let credential = self
+            .hmac_credentials
+            .iter()
+            .find(|credential| credential.key_id == key_id)
+            .ok_or("Unknown signature key id")?;
+
+        let canonical = canonical_request(
+            method,
+            path_and_query,
+            &signed_headers,
+            timestamp,
+            content_sha256,
+        );
+        if !signature::verify(&credential.secret, &canonical, signature) {
+            return Err("Invalid signature");
+        }
+
+        let replay_key = format!("{}:{}", key_id, signature.to_ascii_lowercase());
+        if !self
+            .replay_cache
+            .check_and_insert(&replay_key, now, signed_at + self.max_skew_secs)
+        {
+            return Err("Replayed signature");
+        }
+
+        Ok(Identity {
+            credential: credential.key_id.clone(),
+            consumer: self.consumer_by_id(credential.consumer_id),
+            ..Default::default()
+        })
+    }
+
+    pub fn validate_client_certificate(&self, names: &[String]) -> Option<Identity> {
+        let certificate = self
+            .client_certificates
+            .iter()
+            .find(|certificate| names.contains(&certificate.subject))?;
+
+        Some(Identity {
+            credential: certificate.subject.clone(),
+            consumer: self.consumer_by_id(certificate.consumer_id),
+            ..Default::default()
+        })
+    }
+
+    pub fn consumer_by_name(&self, name: &str) -> Option<DbConsumer> {
+        self.consumers
+            .iter()
+            .find(|consumer| consumer.name == name)
+            .cloned()
+    }
+
+    fn consumer_by_id(&self, id: Option<i64>) -> Option<DbConsumer> {
+        let id = id?;
+        self.consumers
+            .iter()
+            .find(|consumer| consumer.id == id)
+            .cloned()
+    }
+
+    fn identity(&self, api_key: &ApiKey) -> Identity {
+        Identity {
+            credential: api_key.name.clone(),
+            consumer: api_key.consumer.as_ref().and_then(|name| {
+                self.consumers
+                    .iter()
+                    .find(|consumer| &consumer.name == name)
+                    .cloned()
+            }),
+            rate_limit: api_key.rate_limit,
+            quota: api_key.quota,
+            ..Default::default()
+        }
+    }
+
+    fn migrate(&self, index: usize, key: &str) {
+        let Some(key_hash) = self.hash_key(key) else {
+            return;
+        };
+        let Ok(mut api_keys) = self.api_keys.write() else {
+            return;
+        };
+        let Some(api_key) = api_keys.get_mut(index).filter(|api_key| {
+            HashScheme::detect(&api_key.key_hash) == Some(HashScheme::LegacySha256)
+        }) else {
+            return;
+        };
+
+        let legacy_hash = std::mem::replace(&mut api_key.key_hash, key_hash.clone());
+        if index < self.stored_keys_from {
+            tracing::warn!(
+                name = %api_key.name,
+                "API key in config.yml uses a legacy SHA-256 hash, migrated in memory only; regenerate its key_hash"
+            );
+            return;
+        }
+
+        tracing::info!(name = %api_key.name, "Migrating legacy SHA-256 API key hash");
+        if let Ok(mut migrations) = self.migrations.lock() {
+            migrations.push(KeyMigration {
+                name: api_key.name.clone(),
+                legacy_hash,
+                key_hash,
+            });
+        }
+    }
+
+    pub fn take_migrations(&self) -> Vec<KeyMigration> {
+        self.migrations
+            .lock()
+            .map(|mut migrations| std::mem::take(&mut *migrations))
+            .unwrap_or_default()
+    }
+
+    pub fn requeue_migrations(&self, pending: Vec<KeyMigration>) {
+        if let Ok(mut migrations) = self.migrations.lock() {
+            migrations.extend(pending);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::Json;
+
+    fn validator(key_hash: &str) -> AuthValidator {
+        AuthValidator::new(&AuthConfig {
+            enabled: true,
+            pepper: Some(String::from("pepper")),
+            api_keys: vec![ApiKey {
+                name: String::from("test-client"),
+                key_hash: key_hash.to_string(),
+                consumer: None,
+                rate_limit: None,
+                quota: None,
+            }],
+            encryption_key: None,
+            signature: Default::default(),
+            introspection: None,
+            identity_headers: Default::default(),
+        })
+    }
+
+    #[test]
+    fn test_validate_migrates_legacy_hash() {
+        let validator =
+            validator("20eb0536650933acad4ae017f3df774426ea2e4187d13728c99a6baf5e37b2b3");
+
+        assert!(validator.validate("wrong").is_none());
+        assert!(validator.validate("my-test-key").is_some());
+
+        let migrated = validator.api_keys.read().unwrap()[0].key_hash.clone();
+        assert_eq!(migrated, hash_api_key("my-test-key", "pepper"));
+        assert!(validator.validate("my-test-key").is_some());
+        // Config keys can't be written back, so nothing is queued.
+        assert!(validator.take_migrations().is_empty());
+    }
+
+    #[test]
+    fn test_validate_without_pepper_keeps_legacy_hash() {
+        let legacy_hash = "20eb0536650933acad4ae017f3df774426ea2e4187d13728c99a6baf5e37b2b3";
+        let mut validator = validator(legacy_hash);
+        validator.pepper = None;
+
+        assert!(validator.validate("my-test-key").is_some());
+        assert_eq!(validator.api_keys.read().unwrap()[0].key_hash, legacy_hash);
+        assert!(validator.hash_key("my-test-key").is_none());
+    }
+
+    #[test]
+    fn test_validate_hmac_hash() {
+        let validator = validator(&hash_api_key("secret", "pepper"));
+        assert!(validator.validate("secret").is_some());
+        assert!(validator.validate("other").is_none());
+    }
+
+    #[test]
+    fn test_validate_resolves_consumer() {
+        let consumer = DbConsumer {
+            id: 7,
+            name: String::from("partner"),
+            metadata: Default::default(),
+            tags: Default::default(),
+            rate_limit: None,
+            quota: None,
+        };
+        let key = DbApiKey {
+            id: 1,
+            name: String::from("partner-key"),
+            key_hash: hash_api_key("partner-secret", "pepper"),
+            consumer_id: Some(7),
+            rate_limit: Some(Json(RateLimit {
+                max_requests: 10,
+                window_secs: 60,
+            })),
+            quota: None,
+        };
+        let validator = validator(&hash_api_key("secret", "pepper"))
+            .with_database_keys(vec![consumer], vec![key]);
+
+        let identity = validator.validate("partner-secret").unwrap();
+        assert_eq!(identity.credential, "partner-key");
+        assert_eq!(identity.consumer.unwrap().id, 7);
+        assert_eq!(identity.rate_limit.unwrap().max_requests, 10);
+        assert!(validator.validate("secret").unwrap().consumer.is_none());
+    }
+
+    #[test]
+    fn test_validate_basic() {
+        let credential = DbBasicAuthCredential {
+            id: 1,
+            username: String::from("legacy"),
+            password_hash: hash::hash_password("hunter2").unwrap(),
+            consumer_id: None,
+        };
+        let validator =
+            validator(&hash_api_key("secret", "pepper")).with_basic_credentials(vec![credential]);
+
+        let header = format!("Basic {}", STANDARD.encode("legacy:hunter2"));
+        assert_eq!(
+            validator.validate_basic(&header).unwrap().credential,
+            "legacy"
+        );
+
+        let header = format!("basic {}", STANDARD.encode("legacy:hunter2"));
+        assert!(validator.validate_basic(&header).is_some());
+
+        let header = format!("Basic {}", STANDARD.encode("legacy:wrong"));
+        assert!(validator.validate_basic(&header).is_none());
+        let header = format!("Basic {}", STANDARD.encode("unknown:hunter2"));
+        assert!(validator.validate_basic(&header).is_none());
+        assert!(validator.validate_basic("Bearer token").is_none());
+    }
+
+    #[test]
+    fn test_validate_signature() {
+        let cipher = SecretCipher::new("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let credential = DbHmacCredential {
+            id: 1,
+            key_id: String::from("partner"),
+            secret_encrypted: cipher.encrypt("shared-secret").unwrap(),
+            consumer_id: None,
+        };
+        let validator = validator(&hash_api_key("secret", "pepper"))
+            .with_hmac_credentials(vec![credential], Some(&cipher));
+
+        let content_sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let canonical = canonical_request(
+            "GET",
+            "/orders",
+            &[("host", "api.example.com")],
+            "1000",
+            content_sha256,
+        );
+        let signature = signature::sign("shared-secret", &canonical);
+        let headers = [
+            (KEY_ID_HEADER, "partner"),
+            (TIMESTAMP_HEADER, "1000"),
+            (SIGNED_HEADERS_HEADER, "host"),
+            (CONTENT_SHA256_HEADER, content_sha256),
+            (SIGNATURE_HEADER, signature.as_str()),
+            ("host", "api.example.com"),
+        ];
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(header, _)| *header == name)
+                .map(|(_, value)| *value)
+        };
+
+        assert_eq!(
+            validator
+                .validate_signature("GET", "/orders", header, 2000)
+                .unwrap_err(),
+            "Stale signature timestamp"
+        );
+        assert_eq!(
+            validator
+                .validate_signature("POST", "/orders", header, 1010)
+                .unwrap_err(),
+            "Invalid signature"
+        );
+        assert_eq!(
+            validator
+                .validate_signature("GET", "/orders", header, 1010)
+                .unwrap()
+                .credential,
+            "partner"
+        );
+        assert_eq!(
+            validator
+                .validate_signature("GET", "/orders", header, 1020)
+                .unwrap_err(),
+            "Replayed signature"
+        );
+    }
+
+    #[test]
+    fn test_validate_client_certificate() {
+        let certificate = DbClientCertificate {
+            id: 1,
+            subject: String::from("partner.example.com"),
+            consumer_id: None,
+        };
+        let validator = validator(&hash_api_key("secret", "pepper"))
+            .with_client_certificates(vec![certificate]);
+
+        let names = vec![
+            String::from("Partner Inc"),
+            String::from("partner.example.com"),
+        ];
+        assert_eq!(
+            validator
+                .validate_client_certificate(&names)
+                .unwrap()
+                .credential,
+            "partner.example.com"
+        );
+        assert!(
+            validator
+                .validate_client_certificate(&[String::from("other.example.com")])
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+const ENCRYPTED_PREFIX: &str = "aes256gcm$";
+const NONCE_LEN: usize = 12;
+
+#[derive(Clone)]
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    pub fn new(encoded_key: &str) -> Result<Self, String> {
+        let key = STANDARD
+            .decode(encoded_key.trim())
+            .map_err(|e| format!("Invalid encryption key: {}", e))?;
+        let cipher = Aes256Gcm::new_from_slice(&key)
+            .map_err(|_| String::from("Encryption key must be 32 bytes"))?;
+        Ok(Self { cipher })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| String::from("Failed to encrypt secret"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(payload)))
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String, String> {
+        let encoded = stored
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or("Unknown secret format")?;
+        let payload = STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("Invalid secret encoding: {}", e))?;
+        if payload.len() <= NONCE_LEN {
+            return Err(String::from("Secret is truncated"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| String::from("Failed to decrypt secret"))?;
+        String::from_utf8(plaintext).map_err(|_| String::from("Secret is not valid UTF-8"))
+    }
+}
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let cipher = SecretCipher::new(KEY).unwrap();
+        let stored = cipher.encrypt("shared-secret").unwrap();
+
+        assert!(stored.starts_with(ENCRYPTED_PREFIX));
+        assert!(!stored.contains("shared-secret"));
+        assert_eq!(cipher.decrypt(&stored).unwrap(), "shared-secret");
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_key_fails() {
+        let stored = SecretCipher::new(KEY).unwrap().encrypt("secret").unwrap();
+        let other = SecretCipher::new("ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=").unwrap();
+
+        assert!(other.decrypt(&stored).is_err());
+    }
+
+    #[test]
+    fn test_new_rejects_short_key() {
+        assert!(SecretCipher::new("c2hvcnQ=").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::Mutex;
+// imports
+use crate::auth::hash::{constant_time_eq, hmac_sha256};
+
+pub const KEY_ID_HEADER: &str = "x-cirith-key-id";
+pub const TIMESTAMP_HEADER: &str = "x-cirith-timestamp";
+pub const SIGNED_HEADERS_HEADER: &str = "x-cirith-signed-headers";
+pub const CONTENT_SHA256_HEADER: &str = "x-cirith-content-sha256";
+pub const SIGNATURE_HEADER: &str = "x-cirith-signature";
+
+pub const SIGNATURE_HEADERS: &[&str] = &[
+    KEY_ID_HEADER,
+    TIMESTAMP_HEADER,
+    SIGNED_HEADERS_HEADER,
+    CONTENT_SHA256_HEADER,
+    SIGNATURE_HEADER,
+];
+
+pub fn canonical_request(
+    method: &str,
+    path_and_query: &str,
+    headers: &[(&str, &str)],
+    timestamp: &str,
+    content_sha256: &str,
+) -> String {
+    let mut canonical = format!("{}\n{}\n", method.to_ascii_uppercase(), path_and_query);
+    for (name, value) in headers {
+        canonical.push_str(&format!("{}:{}\n", name.to_ascii_lowercase(), value.trim()));
+    }
+    canonical.push_str(&format!(
+        "{}\n{}",
+        timestamp,
+        content_sha256.to_ascii_lowercase()
+    ));
+    canonical
+}
+
+pub fn sign(secret: &str, canonical: &str) -> String {
+    hmac_sha256(canonical, secret)
+}
+
+pub fn verify(secret: &str, canonical: &str, signature: &str) -> bool {
+    constant_time_eq(&sign(secret, canonical), &signature.to_ascii_lowercase())
+}
+
+pub struct ContentDigest {
+    hasher: Sha256,
+}
+
+impl ContentDigest {
+    pub fn new() -> Self {
+        Self {
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
+
+    pub fn matches(self, expected: &str) -> bool {
+        let actual = format!("{:x}", self.hasher.finalize());
+        constant_time_eq(&actual, &expected.to_ascii_lowercase())
+    }
+}
+
+impl Default for ContentDigest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const REPLAY_SHARDS: usize = 16;
+
+#[derive(Debug, Default)]
+struct ReplayShard {
+    seen: HashMap<String, u64>,
+    expiries: BTreeMap<u64, Vec<String>>,
+}
+
+impl ReplayShard {
+    fn evict_expired(&mut self, now: u64) {
+        let live = self.expiries.split_off(&now);
+        for (expiry, signatures) in std::mem::replace(&mut self.expiries, live) {
+            for signature in signatures {
+                if self.seen.get(&signature) == Some(&expiry) {
+                    self.seen.remove(&signature);
+                }
+            }
+        }
+    }
+}
+
+/// Remembers signatures until they expire. Entries are grouped by expiry
+/// second so expired groups are dropped wholesale, and each shard holds at
+/// most its share of `capacity`; a full shard rejects new signatures.
+#[derive(Debug)]
+pub struct ReplayCache {
+    shards: Vec<Mutex<ReplayShard>>,
+    hasher: RandomState,
+    max_entries_per_shard: usize,
+}
+
+impl ReplayCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shards: (0..REPLAY_SHARDS)
+                .map(|_| Mutex::new(ReplayShard::default()))
+                .collect(),
+            hasher: RandomState::new(),
+            max_entries_per_shard: capacity.div_ceil(REPLAY_SHARDS).max(1),
+        }
+    }
+
+    pub fn check_and_insert(&self, signature: &str, now: u64, expires_at: u64) -> bool {
+        let shard = &self.shards[self.hasher.hash_one(signature) as usize % REPLAY_SHARDS];
+        let mut shard = match shard.lock() {
+            Ok(guard) => guard,
+            Err(_) => return false,
+        };
+
+        if shard
+            .expiries
+            .first_key_value()
+            .is_some_and(|(expiry, _)| *expiry < now)
+        {
+            shard.evict_expired(now);
+        }
+        if shard.seen.contains_key(signature) || shard.seen.len() >= self.max_entries_per_shard {
+            return false;
+        }
+
+        shard.seen.insert(signature.to_string(), expires_at);
+        shard
+            .expiries
+            .entry(expires_at)
+            .or_default()
+            .push(signature.to_string());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_request() {
+        let canonical = canonical_request(
+            "post",
+            "/orders?id=1",
+            &[("Content-Type", " application/json ")],
+            "1700000000",
+            "ABC",
+        );
+
+        assert_eq!(
+            canonical,
+            "POST\n/orders?id=1\ncontent-type:application/json\n1700000000\nabc"
+        );
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let canonical = canonical_request("GET", "/", &[], "1700000000", "");
+        let signature = sign("secret", &canonical);
+
+        assert!(verify("secret", &canonical, &signature));
+        assert!(!verify("other", &canonical, &signature));
+    }
+
+    #[test]
+    fn test_content_digest() {
+        let mut digest = ContentDigest::new();
+        digest.update(b"hello ");
+        digest.update(b"world");
+
+        assert!(digest.matches("b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"));
+    }
+
+    #[test]
+    fn test_replay_cache_rejects_repeated_signature() {
+        let cache = ReplayCache::new(100);
+
+        assert!(cache.check_and_insert("sig", 100, 400));
+        assert!(!cache.check_and_insert("sig", 200, 500));
+        assert!(cache.check_and_insert("sig", 401, 700));
+    }
+
+    #[test]
+    fn test_replay_cache_is_bounded() {
+        let cache = ReplayCache::new(1);
+
+        assert!(cache.check_and_insert("first", 100, 400));
+        assert!(!cache.check_and_insert("first", 100, 400));
+        let rejected = (0..64)
+            .filter(|i| !cache.check_and_insert(&format!("sig-{}", i), 100, 400))
+            .count();
+        assert!(rejected > 0);
+
+        assert!(cache.check_and_insert("first", 401, 700));
+    }
+}
@@ -1,10 +1,23 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+// imports
+use crate::auth::hash::HashScheme;
+use crate::auth::identity::{IdentityField, default_identity_headers};
+use crate::auth::secret::SecretCipher;
+use crate::ip_filter::Cidr;
+use crate::quota::Quota;
+use crate::transform::ResponseHeaderRules;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+    #[serde(default)]
+    pub response_headers: ResponseHeaderRules,
     pub auth: AuthConfig,
     pub admin: AdminConfig,
 }
@@ -15,6 +28,45 @@ pub struct ServerConfig {
     pub gateway_port: u16,
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    #[serde(default)]
+    pub forwarded_header: ForwardedHeader,
+    #[serde(default)]
+    pub proxy_protocol: bool,
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+}
+
+/// The header trusted proxies use to pass on the client address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForwardedHeader {
+    #[default]
+    XForwardedFor,
+    Forwarded,
+}
+
+impl ForwardedHeader {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::XForwardedFor => "x-forwarded-for",
+            Self::Forwarded => "forwarded",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    pub port: u16,
+    pub cert_path: String,
+    pub key_path: String,
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+    #[serde(default)]
+    pub require_client_cert: bool,
 }
 
 fn default_timeout() -> u64 {
@@ -30,24 +82,370 @@ pub struct DatabaseConfig {
 pub struct RateLimitConfig {
     pub max_requests: u64,
     pub window_secs: u64,
+    #[serde(default)]
+    pub key_by: RateLimitKey,
+    #[serde(default = "default_max_keys")]
+    pub max_keys: usize,
+    #[serde(default)]
+    pub redis: Option<RedisConfig>,
+    #[serde(default = "default_quota_flush")]
+    pub quota_flush_secs: u64,
+    #[serde(default = "default_reload")]
+    pub reload_secs: u64,
+    #[serde(default)]
+    pub shadow: bool,
+    #[serde(default)]
+    pub exempt: RateLimitExemptions,
+}
+
+fn default_max_keys() -> usize {
+    100_000
+}
+
+fn default_quota_flush() -> u64 {
+    10
+}
+
+fn default_reload() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RedisConfig {
+    pub url: String,
+    #[serde(default)]
+    pub failure_mode: FailureMode,
+    #[serde(default = "default_redis_key_prefix")]
+    pub key_prefix: String,
+    #[serde(default = "default_redis_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_redis_key_prefix() -> String {
+    String::from("cirith:rate-limit:")
+}
+
+fn default_redis_timeout() -> u64 {
+    50
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureMode {
+    Open,
+    #[default]
+    Closed,
+}
+
+impl RateLimitConfig {
+    pub fn policy(&self) -> RateLimitPolicy {
+        RateLimitPolicy {
+            max_requests: self.max_requests,
+            window_secs: self.window_secs,
+            key_by: self.key_by.clone(),
+            shadow: self.shadow,
+            exempt: self.exempt.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub max_requests: u64,
+    pub window_secs: u64,
+}
+
+impl RateLimit {
+    pub fn is_valid(&self) -> bool {
+        self.max_requests > 0 && self.window_secs > 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitPolicy {
+    pub max_requests: u64,
+    pub window_secs: u64,
+    #[serde(default)]
+    pub key_by: RateLimitKey,
+    #[serde(default)]
+    pub shadow: bool,
+    #[serde(default)]
+    pub exempt: RateLimitExemptions,
+}
+
+impl RateLimitPolicy {
+    pub fn limit(&self) -> RateLimit {
+        RateLimit {
+            max_requests: self.max_requests,
+            window_secs: self.window_secs,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.limit().is_valid()
+            && !matches!(&self.key_by, RateLimitKey::Header(name) if name.trim().is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitExemptions {
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    #[serde(default)]
+    pub cidrs: Vec<Cidr>,
+}
+
+impl RateLimitExemptions {
+    pub fn matches(&self, api_key: Option<&str>, ip: Option<IpAddr>) -> bool {
+        api_key.is_some_and(|key| self.api_keys.iter().any(|exempt| exempt == key))
+            || ip.is_some_and(|ip| self.cidrs.iter().any(|cidr| cidr.contains(ip)))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RateLimitKey {
+    #[default]
+    Ip,
+    ApiKey,
+    Consumer,
+    Header(String),
+    Route,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConcurrencyConfig {
+    #[serde(default)]
+    pub upstreams: HashMap<String, ConcurrencyLimit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencyLimit {
+    pub max_in_flight: usize,
+    #[serde(default)]
+    pub queue_size: usize,
+    #[serde(default = "default_queue_timeout")]
+    pub queue_timeout_ms: u64,
+}
+
+fn default_queue_timeout() -> u64 {
+    1000
+}
+
+impl ConcurrencyLimit {
+    pub fn is_valid(&self) -> bool {
+        self.max_in_flight > 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadShedding {
+    #[serde(default = "default_min_limit")]
+    pub min_limit: usize,
+    pub max_limit: usize,
+    #[serde(default = "default_latency_tolerance")]
+    pub latency_tolerance: f64,
+    #[serde(default = "default_shed_cooldown")]
+    pub cooldown_ms: u64,
+    #[serde(default)]
+    pub premium_tags: Vec<String>,
+}
+
+fn default_min_limit() -> usize {
+    1
+}
+
+fn default_shed_cooldown() -> u64 {
+    1000
+}
+
+fn default_latency_tolerance() -> f64 {
+    2.0
+}
+
+impl LoadShedding {
+    pub fn is_valid(&self) -> bool {
+        self.min_limit > 0 && self.max_limit >= self.min_limit && self.latency_tolerance >= 1.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub exposed_headers: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+fn default_cors_methods() -> Vec<String> {
+    ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+impl CorsPolicy {
+    pub fn is_valid(&self) -> bool {
+        !self.allowed_origins.is_empty()
+            && !self.allowed_methods.is_empty()
+            && self
+                .allowed_origins
+                .iter()
+                .all(|origin| origin.matches('*').count() <= 1)
+            && (!self.allow_credentials || !self.allows_any_origin())
+    }
+
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    /// Origins are compared case-insensitively, for exact and wildcard
+    /// entries alike.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.allowed_origins.iter().any(|allowed| {
+            if allowed == "*" {
+                return true;
+            }
+            let allowed = allowed.to_ascii_lowercase();
+            let Some((prefix, suffix)) = allowed.split_once('*') else {
+                return allowed == origin;
+            };
+            origin.len() > prefix.len() + suffix.len()
+                && origin.starts_with(prefix)
+                && origin.ends_with(suffix)
+                && !origin[prefix.len()..origin.len() - suffix.len()].contains('/')
+        })
+    }
+
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.allowed_methods
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(method))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthConfig {
     pub enabled: bool,
     #[serde(default)]
+    pub pepper: Option<String>,
+    #[serde(default)]
     pub api_keys: Vec<ApiKey>,
+    #[serde(default)]
+    pub encryption_key: Option<String>,
+    #[serde(default)]
+    pub signature: SignatureConfig,
+    #[serde(default)]
+    pub introspection: Option<IntrospectionConfig>,
+    #[serde(default = "default_identity_headers")]
+    pub identity_headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignatureConfig {
+    #[serde(default = "default_max_skew")]
+    pub max_skew_secs: u64,
+    #[serde(default = "default_max_signed_body_bytes")]
+    pub max_body_bytes: usize,
+    #[serde(default = "default_replay_cache_size")]
+    pub replay_cache_size: usize,
+}
+
+impl Default for SignatureConfig {
+    fn default() -> Self {
+        Self {
+            max_skew_secs: default_max_skew(),
+            max_body_bytes: default_max_signed_body_bytes(),
+            replay_cache_size: default_replay_cache_size(),
+        }
+    }
+}
+
+fn default_max_skew() -> u64 {
+    300
+}
+
+fn default_max_signed_body_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_replay_cache_size() -> usize {
+    100_000
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IntrospectionConfig {
+    pub url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default = "default_introspection_cache_ttl")]
+    pub cache_ttl_secs: u64,
+    #[serde(default = "default_introspection_connect_timeout")]
+    pub connect_timeout_ms: u64,
+    #[serde(default = "default_introspection_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_introspection_cache_ttl() -> u64 {
+    60
+}
+
+fn default_introspection_connect_timeout() -> u64 {
+    1000
+}
+
+fn default_introspection_timeout() -> u64 {
+    3000
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AdminConfig {
-    pub token: String,
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub tokens: Vec<AdminToken>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdminToken {
+    pub name: String,
+    pub token_hash: String,
+    pub role: AdminRole,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AdminRole {
+    ReadOnly,
+    RoutesAdmin,
+    KeysAdmin,
+    Superuser,
+}
+
+impl AdminRole {
+    pub fn allows(self, required: AdminRole) -> bool {
+        self == AdminRole::Superuser || required == AdminRole::ReadOnly || self == required
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ApiKey {
     pub name: String,
     pub key_hash: String,
+    #[serde(default)]
+    pub consumer: Option<String>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+    #[serde(default)]
+    pub quota: Option<Quota>,
 }
 
 impl Config {
@@ -59,12 +457,92 @@ impl Config {
     }
 
     pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.auth.pepper {
+            Some(pepper) if pepper.trim().is_empty() => {
+                return Err("auth.pepper cannot be empty".into());
+            }
+            None if self
+                .auth
+                .api_keys
+                .iter()
+                .map(|key| key.key_hash.as_str())
+                .chain(
+                    self.admin
+                        .tokens
+                        .iter()
+                        .map(|token| token.token_hash.as_str()),
+                )
+                .any(|hash| HashScheme::detect(hash) == Some(HashScheme::HmacSha256)) =>
+            {
+                return Err("auth.pepper is required for hmac-sha256 hashes".into());
+            }
+            _ => {}
+        }
         if self.rate_limit.max_requests == 0 {
             return Err("max_requests cannot be 0".into());
         }
         if self.rate_limit.window_secs == 0 {
             return Err("window_secs cannot be 0".into());
         }
+        if self.rate_limit.max_keys == 0 {
+            return Err("max_keys cannot be 0".into());
+        }
+        if self.rate_limit.quota_flush_secs == 0 {
+            return Err("quota_flush_secs cannot be 0".into());
+        }
+        if self.rate_limit.reload_secs == 0 {
+            return Err("reload_secs cannot be 0".into());
+        }
+        if let Some(tls) = &self.server.tls
+            && tls.require_client_cert
+            && tls.client_ca_path.is_none()
+        {
+            return Err("require_client_cert needs client_ca_path".into());
+        }
+        if self.admin.token.is_none() && self.admin.tokens.is_empty() {
+            return Err("admin.token or admin.tokens must be set".into());
+        }
+        if let RateLimitKey::Header(name) = &self.rate_limit.key_by
+            && name.trim().is_empty()
+        {
+            return Err("rate_limit.key_by header name cannot be empty".into());
+        }
+        for api_key in &self.auth.api_keys {
+            if let Some(limit) = &api_key.rate_limit
+                && !limit.is_valid()
+            {
+                return Err(format!("Invalid rate limit for API key {}", api_key.name).into());
+            }
+            if let Some(quota) = &api_key.quota
+                && !quota.is_valid()
+            {
+                return Err(format!("Invalid quota for API key {}", api_key.name).into());
+            }
+        }
+        for (upstream, limit) in &self.concurrency.upstreams {
+            if !limit.is_valid() {
+                return Err(format!("Invalid concurrency limit for upstream {}", upstream).into());
+            }
+        }
+        for proxy in &self.server.trusted_proxies {
+            proxy.parse::<Cidr>()?;
+        }
+        if self.server.proxy_protocol && self.server.trusted_proxies.is_empty() {
+            return Err("server.proxy_protocol needs server.trusted_proxies".into());
+        }
+        for field in self.auth.identity_headers.values() {
+            field.parse::<IdentityField>()?;
+        }
+        self.response_headers.transforms.validate()?;
+        if let Some(redis) = &self.rate_limit.redis {
+            url::Url::parse(&redis.url)?;
+        }
+        if let Some(introspection) = &self.auth.introspection {
+            url::Url::parse(&introspection.url)?;
+        }
+        if let Some(key) = &self.auth.encryption_key {
+            SecretCipher::new(key)?;
+        }
         Ok(())
     }
 }
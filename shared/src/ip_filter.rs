@@ -0,0 +1,354 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+// imports
+use crate::storage::DbIpRule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(type_name = "TEXT", rename_all = "kebab-case")]
+pub enum IpAction {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, String> {
+        let addr = addr.to_canonical();
+        let (bits, width) = address_bits(addr);
+        if prefix_len > width {
+            return Err(format!("Prefix length {} is too long", prefix_len));
+        }
+
+        let network = match addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(((bits & mask(prefix_len)) >> 96) as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits & mask(prefix_len))),
+        };
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let (network, network_width) = address_bits(self.network);
+        let (bits, width) = address_bits(ip.to_canonical());
+        network_width == width && bits & mask(self.prefix_len) == network
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid IP address: {}", addr))?;
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid prefix length: {}", prefix_len))?,
+            None => address_bits(addr.to_canonical()).1,
+        };
+
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl Serialize for Cidr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    children: [Option<Box<Node>>; 2],
+    terminal: bool,
+}
+
+impl Node {
+    fn insert(&mut self, bits: u128, prefix_len: u8) {
+        let mut node = self;
+        for depth in 0..prefix_len {
+            node = node.children[bit(bits, depth)].get_or_insert_with(Box::default);
+        }
+        node.terminal = true;
+    }
+
+    fn matches(&self, bits: u128, width: u8) -> bool {
+        let mut node = self;
+        for depth in 0..width {
+            if node.terminal {
+                return true;
+            }
+            match &node.children[bit(bits, depth)] {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+        node.terminal
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CidrTrie {
+    v4: Node,
+    v6: Node,
+    len: usize,
+}
+
+impl CidrTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, cidr: &Cidr) {
+        let (bits, _) = address_bits(cidr.network);
+        match cidr.network {
+            IpAddr::V4(_) => self.v4.insert(bits, cidr.prefix_len),
+            IpAddr::V6(_) => self.v6.insert(bits, cidr.prefix_len),
+        }
+        self.len += 1;
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        let (bits, width) = address_bits(ip);
+        match ip {
+            IpAddr::V4(_) => self.v4.matches(bits, width),
+            IpAddr::V6(_) => self.v6.matches(bits, width),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl FromIterator<Cidr> for CidrTrie {
+    fn from_iter<I: IntoIterator<Item = Cidr>>(iter: I) -> Self {
+        let mut trie = Self::new();
+        for cidr in iter {
+            trie.insert(&cidr);
+        }
+        trie
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IpFilter {
+    allow: CidrTrie,
+    deny: CidrTrie,
+}
+
+impl IpFilter {
+    pub fn add(&mut self, cidr: &Cidr, action: IpAction) {
+        match action {
+            IpAction::Allow => self.allow.insert(cidr),
+            IpAction::Deny => self.deny.insert(cidr),
+        }
+    }
+
+    /// An unknown client IP is only allowed when there is no allow-list.
+    pub fn is_allowed(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) if self.deny.contains(ip) => false,
+            Some(ip) => self.allow.is_empty() || self.allow.contains(ip),
+            None => self.allow.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IpRules {
+    global: IpFilter,
+    routes: HashMap<i64, IpFilter>,
+}
+
+impl IpRules {
+    pub fn new(rules: &[DbIpRule]) -> Self {
+        let mut ip_rules = Self::default();
+        for rule in rules {
+            let cidr = match rule.cidr.parse::<Cidr>() {
+                Ok(cidr) => cidr,
+                Err(e) => {
+                    tracing::warn!(id = rule.id, cidr = %rule.cidr, "Skipping IP rule: {}", e);
+                    continue;
+                }
+            };
+
+            let filter = match rule.route_id {
+                Some(route_id) => ip_rules.routes.entry(route_id).or_default(),
+                None => &mut ip_rules.global,
+            };
+            filter.add(&cidr, rule.action);
+        }
+        ip_rules
+    }
+
+    pub fn is_allowed(&self, ip: Option<IpAddr>, route_id: Option<i64>) -> bool {
+        if !self.global.is_allowed(ip) {
+            return false;
+        }
+
+        route_id
+            .and_then(|id| self.routes.get(&id))
+            .is_none_or(|filter| filter.is_allowed(ip))
+    }
+}
+
+fn address_bits(ip: IpAddr) -> (u128, u8) {
+    match ip {
+        IpAddr::V4(ipv4) => ((u32::from(ipv4) as u128) << 96, 32),
+        IpAddr::V6(ipv6) => (u128::from(ipv6), 128),
+    }
+}
+
+fn mask(prefix_len: u8) -> u128 {
+    match prefix_len {
+        0 => 0,
+        len => u128::MAX << (128 - len as u32),
+    }
+}
+
+fn bit(bits: u128, depth: u8) -> usize {
+    ((bits >> (127 - depth as u32)) & 1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn rule(id: i64, cidr: &str, action: IpAction, route_id: Option<i64>) -> DbIpRule {
+        DbIpRule {
+            id,
+            cidr: cidr.to_string(),
+            action,
+            route_id,
+        }
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        assert_eq!(
+            "10.1.2.3/8".parse::<Cidr>().unwrap().to_string(),
+            "10.0.0.0/8"
+        );
+        assert_eq!(
+            "192.168.1.1".parse::<Cidr>().unwrap().to_string(),
+            "192.168.1.1/32"
+        );
+        assert_eq!(
+            "2001:db8::1/32".parse::<Cidr>().unwrap().to_string(),
+            "2001:db8::/32"
+        );
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("not-an-ip/8".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_cidr_serde() {
+        let cidrs: Vec<Cidr> = serde_json::from_str(r#"["10.1.2.3/8", "::1"]"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cidrs).unwrap(),
+            r#"["10.0.0.0/8","::1/128"]"#
+        );
+        assert!(serde_json::from_str::<Cidr>(r#""10.0.0.0/33""#).is_err());
+    }
+
+    #[test]
+    fn test_trie_matches_prefixes() {
+        let trie: CidrTrie = ["10.0.0.0/8", "192.168.1.0/24", "2001:db8::/32"]
+            .iter()
+            .map(|cidr| cidr.parse().unwrap())
+            .collect();
+
+        assert!(trie.contains(ip("10.200.3.4")));
+        assert!(trie.contains(ip("192.168.1.77")));
+        assert!(!trie.contains(ip("192.168.2.1")));
+        assert!(trie.contains(ip("2001:db8:1::1")));
+        assert!(!trie.contains(ip("2001:db9::1")));
+        assert!(trie.contains(ip("::ffff:10.0.0.1")));
+    }
+
+    #[test]
+    fn test_zero_prefix_matches_everything() {
+        let trie: CidrTrie = ["0.0.0.0/0".parse().unwrap()].into_iter().collect();
+        assert!(trie.contains(ip("8.8.8.8")));
+        assert!(!trie.contains(ip("::1")));
+    }
+
+    #[test]
+    fn test_deny_takes_precedence_over_allow() {
+        let rules = IpRules::new(&[
+            rule(1, "10.0.0.0/8", IpAction::Allow, None),
+            rule(2, "10.0.0.0/24", IpAction::Deny, None),
+        ]);
+
+        assert!(rules.is_allowed(Some(ip("10.1.0.1")), None));
+        assert!(!rules.is_allowed(Some(ip("10.0.0.1")), None));
+        assert!(!rules.is_allowed(Some(ip("172.16.0.1")), None));
+    }
+
+    #[test]
+    fn test_route_rules_apply_only_to_their_route() {
+        let rules = IpRules::new(&[
+            rule(1, "203.0.113.0/24", IpAction::Allow, Some(7)),
+            rule(2, "198.51.100.0/24", IpAction::Deny, None),
+        ]);
+
+        assert!(rules.is_allowed(Some(ip("203.0.113.5")), Some(7)));
+        assert!(!rules.is_allowed(Some(ip("8.8.8.8")), Some(7)));
+        assert!(rules.is_allowed(Some(ip("8.8.8.8")), Some(8)));
+        assert!(rules.is_allowed(Some(ip("8.8.8.8")), None));
+        assert!(!rules.is_allowed(Some(ip("198.51.100.1")), Some(8)));
+    }
+
+    #[test]
+    fn test_unknown_ip_is_denied_by_allow_lists() {
+        let rules = IpRules::new(&[
+            rule(1, "203.0.113.0/24", IpAction::Allow, Some(7)),
+            rule(2, "198.51.100.0/24", IpAction::Deny, None),
+        ]);
+
+        assert!(!rules.is_allowed(None, Some(7)));
+        assert!(rules.is_allowed(None, Some(8)));
+
+        let rules = IpRules::new(&[rule(1, "10.0.0.0/8", IpAction::Allow, None)]);
+        assert!(!rules.is_allowed(None, Some(8)));
+    }
+}
@@ -1,5 +1,8 @@
 pub mod auth;
 pub mod config;
 pub mod error;
+pub mod ip_filter;
+pub mod quota;
 pub mod storage;
+pub mod transform;
 pub mod validation;
@@ -0,0 +1,149 @@
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+// imports
+use crate::storage::DbQuotaUsage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+impl QuotaPeriod {
+    pub fn start(self, now: DateTime<Utc>) -> NaiveDate {
+        let today = now.date_naive();
+        match self {
+            Self::Daily => today,
+            Self::Monthly => today.with_day(1).unwrap_or(today),
+        }
+    }
+
+    pub fn end(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Daily => start.succ_opt().unwrap_or(start),
+            Self::Monthly => start.checked_add_months(Months::new(1)).unwrap_or(start),
+        }
+    }
+
+    pub fn resets_at(self, start: NaiveDate) -> DateTime<Utc> {
+        self.end(start).and_time(chrono::NaiveTime::MIN).and_utc()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quota {
+    pub limit: u64,
+    pub period: QuotaPeriod,
+}
+
+impl Quota {
+    pub fn is_valid(&self) -> bool {
+        self.limit > 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaSubject<'a> {
+    ApiKey(&'a str),
+    Consumer(&'a str),
+}
+
+impl fmt::Display for QuotaSubject<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ApiKey(name) => write!(f, "key:{}", name),
+            Self::Consumer(name) => write!(f, "consumer:{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaUsage {
+    pub subject: String,
+    pub limit: u64,
+    pub period: QuotaPeriod,
+    pub period_start: NaiveDate,
+    pub used: u64,
+    pub remaining: u64,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl QuotaUsage {
+    pub fn new(
+        subject: String,
+        quota: Quota,
+        usage: Option<&DbQuotaUsage>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let period_start = quota.period.start(now);
+        let used = usage
+            .filter(|usage| usage.period_start == period_start.to_string())
+            .map(|usage| usage.used.max(0) as u64)
+            .unwrap_or_default();
+
+        Self {
+            subject,
+            limit: quota.limit,
+            period: quota.period,
+            period_start,
+            used,
+            remaining: quota.limit.saturating_sub(used),
+            resets_at: quota.period.resets_at(period_start),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_period_boundaries() {
+        let now = Utc.with_ymd_and_hms(2024, 12, 31, 18, 30, 0).unwrap();
+
+        let daily = QuotaPeriod::Daily.start(now);
+        assert_eq!(daily.to_string(), "2024-12-31");
+        assert_eq!(
+            QuotaPeriod::Daily.resets_at(daily).to_rfc3339(),
+            "2025-01-01T00:00:00+00:00"
+        );
+
+        let monthly = QuotaPeriod::Monthly.start(now);
+        assert_eq!(monthly.to_string(), "2024-12-01");
+        assert_eq!(QuotaPeriod::Monthly.end(monthly).to_string(), "2025-01-01");
+    }
+
+    #[test]
+    fn test_usage_from_previous_period_is_ignored() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 10, 12, 0, 0).unwrap();
+        let quota = Quota {
+            limit: 100,
+            period: QuotaPeriod::Monthly,
+        };
+        let usage = |period_start: &str| DbQuotaUsage {
+            subject: String::from("consumer:partner"),
+            period_start: period_start.to_string(),
+            used: 40,
+        };
+
+        let current = QuotaUsage::new(
+            String::from("consumer:partner"),
+            quota,
+            Some(&usage("2024-02-01")),
+            now,
+        );
+        assert_eq!(current.used, 40);
+        assert_eq!(current.remaining, 60);
+
+        let stale = QuotaUsage::new(
+            String::from("consumer:partner"),
+            quota,
+            Some(&usage("2024-01-01")),
+            now,
+        );
+        assert_eq!(stale.used, 0);
+    }
+}
@@ -1,5 +1,12 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, SqlitePool, sqlite::SqlitePoolOptions};
+use sqlx::{FromRow, Sqlite, SqlitePool, Transaction, sqlite::SqlitePoolOptions, types::Json};
+use std::collections::HashMap;
+// imports
+use crate::auth::AuthMode;
+use crate::config::{ConcurrencyLimit, CorsPolicy, LoadShedding, RateLimit, RateLimitPolicy};
+use crate::ip_filter::IpAction;
+use crate::quota::Quota;
+use crate::transform::{HeaderTransforms, ResponseHeaderRules};
 
 pub struct Database {
     pool: SqlitePool,
@@ -10,6 +17,52 @@ pub struct DbRoute {
     pub id: i64,
     pub path: String,
     pub upstream: String,
+    pub auth: Option<AuthMode>,
+    pub forward_authorization: bool,
+    pub scopes: Json<Vec<String>>,
+    pub rate_limit: Option<Json<RateLimitPolicy>>,
+    pub concurrency: Option<Json<ConcurrencyLimit>>,
+    pub load_shedding: Option<Json<LoadShedding>>,
+    pub request_headers: Option<Json<HeaderTransforms>>,
+    pub response_headers: Option<Json<ResponseHeaderRules>>,
+    pub cors: Option<Json<CorsPolicy>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewRoute {
+    pub path: String,
+    pub upstream: String,
+    pub auth: Option<AuthMode>,
+    pub forward_authorization: bool,
+    pub scopes: Vec<String>,
+    pub policies: RoutePolicies,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RoutePolicies {
+    pub rate_limit: Option<RateLimitPolicy>,
+    pub concurrency: Option<ConcurrencyLimit>,
+    pub load_shedding: Option<LoadShedding>,
+    pub request_headers: Option<HeaderTransforms>,
+    pub response_headers: Option<ResponseHeaderRules>,
+    pub cors: Option<CorsPolicy>,
+}
+
+impl RoutePolicies {
+    pub fn is_valid(&self) -> bool {
+        self.rate_limit.as_ref().is_none_or(|p| p.is_valid())
+            && self.concurrency.is_none_or(|c| c.is_valid())
+            && self.load_shedding.as_ref().is_none_or(|l| l.is_valid())
+            && self
+                .request_headers
+                .as_ref()
+                .is_none_or(|h| h.validate().is_ok())
+            && self
+                .response_headers
+                .as_ref()
+                .is_none_or(|h| h.transforms.validate().is_ok())
+            && self.cors.as_ref().is_none_or(|c| c.is_valid())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -17,9 +70,112 @@ pub struct DbApiKey {
     pub id: i64,
     pub name: String,
     pub key_hash: String,
+    pub consumer_id: Option<i64>,
+    pub rate_limit: Option<Json<RateLimit>>,
+    pub quota: Option<Json<Quota>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbConsumer {
+    pub id: i64,
+    pub name: String,
+    pub metadata: Json<HashMap<String, String>>,
+    pub tags: Json<Vec<String>>,
+    pub rate_limit: Option<Json<RateLimit>>,
+    pub quota: Option<Json<Quota>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbBasicAuthCredential {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: String,
+    pub consumer_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbHmacCredential {
+    pub id: i64,
+    pub key_id: String,
+    pub secret_encrypted: String,
+    pub consumer_id: Option<i64>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbClientCertificate {
+    pub id: i64,
+    pub subject: String,
+    pub consumer_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbIpRule {
+    pub id: i64,
+    pub cidr: String,
+    pub action: IpAction,
+    pub route_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbQuotaUsage {
+    pub subject: String,
+    pub period_start: String,
+    pub used: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbAuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub before: Option<Json<serde_json::Value>>,
+    pub after: Option<Json<serde_json::Value>>,
+    pub source_ip: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewAuditEntry {
+    pub actor: String,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub source_ip: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub entity: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: i64,
+}
+
+const ROUTE_COLUMNS: &str = "id, path, upstream, auth, forward_authorization, scopes, rate_limit, concurrency, load_shedding, request_headers, response_headers, cors";
+const API_KEY_COLUMNS: &str = "id, name, key_hash, consumer_id, rate_limit, quota";
+const CONSUMER_COLUMNS: &str = "id, name, metadata, tags, rate_limit, quota";
+
 impl Database {
+    pub async fn begin(&self) -> Result<DatabaseTx, sqlx::Error> {
+        Ok(DatabaseTx {
+            tx: self.pool.begin().await?,
+        })
+    }
+
+    async fn transaction<T>(
+        &self,
+        f: impl AsyncFnOnce(&mut DatabaseTx) -> Result<T, sqlx::Error>,
+    ) -> Result<T, sqlx::Error> {
+        let mut tx = self.begin().await?;
+        let result = f(&mut tx).await?;
+        tx.commit().await?;
+        Ok(result)
+    }
+
     pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
         let pool = SqlitePoolOptions::new()
             .max_connections(5)
@@ -32,6 +188,39 @@ impl Database {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             path TEXT UNIQUE NOT NULL,
             upstream TEXT NOT NULL,
+            auth TEXT,
+            forward_authorization INTEGER NOT NULL DEFAULT 0,
+            scopes TEXT NOT NULL DEFAULT '[]',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        add_column_if_missing(&pool, "routes", "auth", "TEXT").await?;
+        add_column_if_missing(
+            &pool,
+            "routes",
+            "forward_authorization",
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+        add_column_if_missing(&pool, "routes", "scopes", "TEXT NOT NULL DEFAULT '[]'").await?;
+        add_column_if_missing(&pool, "routes", "rate_limit", "TEXT").await?;
+        add_column_if_missing(&pool, "routes", "concurrency", "TEXT").await?;
+        add_column_if_missing(&pool, "routes", "load_shedding", "TEXT").await?;
+        add_column_if_missing(&pool, "routes", "request_headers", "TEXT").await?;
+        add_column_if_missing(&pool, "routes", "response_headers", "TEXT").await?;
+        add_column_if_missing(&pool, "routes", "cors", "TEXT").await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS consumers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT UNIQUE NOT NULL,
+            metadata TEXT NOT NULL DEFAULT '{}',
+            tags TEXT NOT NULL DEFAULT '[]',
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
         )
             "#,
@@ -45,6 +234,7 @@ impl Database {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             name TEXT UNIQUE NOT NULL,
             key_hash TEXT NOT NULL,
+            consumer_id INTEGER REFERENCES consumers(id),
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
         )
             "#,
@@ -52,56 +242,719 @@ impl Database {
         .execute(&pool)
         .await?;
 
+        add_column_if_missing(
+            &pool,
+            "api_keys",
+            "consumer_id",
+            "INTEGER REFERENCES consumers(id)",
+        )
+        .await?;
+        add_column_if_missing(&pool, "api_keys", "rate_limit", "TEXT").await?;
+        add_column_if_missing(&pool, "consumers", "rate_limit", "TEXT").await?;
+        add_column_if_missing(&pool, "api_keys", "quota", "TEXT").await?;
+        add_column_if_missing(&pool, "consumers", "quota", "TEXT").await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS hmac_credentials (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            key_id TEXT UNIQUE NOT NULL,
+            secret_encrypted TEXT NOT NULL,
+            consumer_id INTEGER REFERENCES consumers(id),
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS client_certificates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            subject TEXT UNIQUE NOT NULL,
+            consumer_id INTEGER REFERENCES consumers(id),
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS basic_auth_credentials (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username TEXT UNIQUE NOT NULL,
+            password_hash TEXT NOT NULL,
+            consumer_id INTEGER REFERENCES consumers(id),
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS ip_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            cidr TEXT NOT NULL,
+            action TEXT NOT NULL,
+            route_id INTEGER REFERENCES routes(id),
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            actor TEXT NOT NULL,
+            action TEXT NOT NULL,
+            entity TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            before TEXT,
+            after TEXT,
+            source_ip TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS quota_usage (
+            subject TEXT PRIMARY KEY,
+            period_start TEXT NOT NULL,
+            used INTEGER NOT NULL DEFAULT 0
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
         Ok(Self { pool })
     }
 
     pub async fn get_routes(&self) -> Result<Vec<DbRoute>, sqlx::Error> {
-        sqlx::query_as::<_, DbRoute>("SELECT id, path, upstream FROM routes")
+        sqlx::query_as::<_, DbRoute>(&format!("SELECT {} FROM routes", ROUTE_COLUMNS))
             .fetch_all(&self.pool)
             .await
     }
 
-    pub async fn add_route(&self, path: &str, upstream: &str) -> Result<DbRoute, sqlx::Error> {
-        sqlx::query_as::<_, DbRoute>(
-            "INSERT INTO routes(path, upstream) VALUES (?, ?) RETURNING id, path, upstream",
-        )
+    pub async fn add_route(&self, route: &NewRoute) -> Result<DbRoute, sqlx::Error> {
+        self.transaction(async |tx| tx.add_route(route).await).await
+    }
+
+    pub async fn get_route(&self, path: &str) -> Result<Option<DbRoute>, sqlx::Error> {
+        sqlx::query_as::<_, DbRoute>(&format!(
+            "SELECT {} FROM routes WHERE path = ?",
+            ROUTE_COLUMNS
+        ))
         .bind(path)
-        .bind(upstream)
-        .fetch_one(&self.pool)
+        .fetch_optional(&self.pool)
         .await
     }
 
+    pub async fn update_route_policies(
+        &self,
+        path: &str,
+        policies: &RoutePolicies,
+    ) -> Result<Option<DbRoute>, sqlx::Error> {
+        self.transaction(async |tx| tx.update_route_policies(path, policies).await)
+            .await
+    }
+
+    pub async fn update_route_rate_limit(
+        &self,
+        path: &str,
+        rate_limit: Option<&RateLimitPolicy>,
+    ) -> Result<Option<DbRoute>, sqlx::Error> {
+        self.transaction(async |tx| tx.update_route_rate_limit(path, rate_limit).await)
+            .await
+    }
+
     pub async fn delete_route(&self, path: &str) -> Result<bool, sqlx::Error> {
-        let result = sqlx::query("DELETE FROM routes WHERE path = ?")
-            .bind(path)
+        self.transaction(async |tx| tx.delete_route(path).await)
+            .await
+    }
+
+    pub async fn get_api_keys(&self) -> Result<Vec<DbApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, DbApiKey>(&format!("SELECT {} FROM api_keys", API_KEY_COLUMNS))
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn get_consumer_api_keys(
+        &self,
+        consumer_id: i64,
+    ) -> Result<Vec<DbApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, DbApiKey>(&format!(
+            "SELECT {} FROM api_keys WHERE consumer_id = ?",
+            API_KEY_COLUMNS
+        ))
+        .bind(consumer_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn add_api_key(
+        &self,
+        name: &str,
+        key_hash: &str,
+        consumer_id: Option<i64>,
+        rate_limit: Option<&RateLimit>,
+        quota: Option<&Quota>,
+    ) -> Result<DbApiKey, sqlx::Error> {
+        self.transaction(async |tx| {
+            tx.add_api_key(name, key_hash, consumer_id, rate_limit, quota)
+                .await
+        })
+        .await
+    }
+
+    pub async fn get_api_key(&self, name: &str) -> Result<Option<DbApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, DbApiKey>(&format!(
+            "SELECT {} FROM api_keys WHERE name = ?",
+            API_KEY_COLUMNS
+        ))
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn delete_api_key(&self, name: &str) -> Result<bool, sqlx::Error> {
+        self.transaction(async |tx| tx.delete_api_key(name).await)
+            .await
+    }
+
+    /// Replaces a key's hash only if it still holds `old_hash`, so a key
+    /// re-created in the meantime is left alone.
+    pub async fn update_api_key_hash(
+        &self,
+        name: &str,
+        old_hash: &str,
+        key_hash: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result =
+            sqlx::query("UPDATE api_keys SET key_hash = ? WHERE name = ? AND key_hash = ?")
+                .bind(key_hash)
+                .bind(name)
+                .bind(old_hash)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_consumers(&self) -> Result<Vec<DbConsumer>, sqlx::Error> {
+        sqlx::query_as::<_, DbConsumer>(&format!("SELECT {} FROM consumers", CONSUMER_COLUMNS))
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn get_consumer(&self, name: &str) -> Result<Option<DbConsumer>, sqlx::Error> {
+        sqlx::query_as::<_, DbConsumer>(&format!(
+            "SELECT {} FROM consumers WHERE name = ?",
+            CONSUMER_COLUMNS
+        ))
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn add_consumer(
+        &self,
+        name: &str,
+        metadata: &HashMap<String, String>,
+        tags: &[String],
+        rate_limit: Option<&RateLimit>,
+        quota: Option<&Quota>,
+    ) -> Result<DbConsumer, sqlx::Error> {
+        sqlx::query_as::<_, DbConsumer>(&format!(
+            "INSERT INTO consumers (name, metadata, tags, rate_limit, quota) VALUES (?, ?, ?, ?, ?) RETURNING {}",
+            CONSUMER_COLUMNS
+        ))
+        .bind(name)
+        .bind(Json(metadata))
+        .bind(Json(tags))
+        .bind(rate_limit.map(Json))
+        .bind(quota.map(Json))
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn update_consumer_rate_limit(
+        &self,
+        name: &str,
+        rate_limit: Option<&RateLimit>,
+    ) -> Result<Option<DbConsumer>, sqlx::Error> {
+        self.transaction(async |tx| tx.update_consumer_rate_limit(name, rate_limit).await)
+            .await
+    }
+
+    pub async fn delete_consumer(&self, name: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for table in [
+            "api_keys",
+            "basic_auth_credentials",
+            "hmac_credentials",
+            "client_certificates",
+        ] {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE consumer_id = (SELECT id FROM consumers WHERE name = ?)",
+                table
+            ))
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let result = sqlx::query("DELETE FROM consumers WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_basic_auth_credentials(
+        &self,
+    ) -> Result<Vec<DbBasicAuthCredential>, sqlx::Error> {
+        sqlx::query_as::<_, DbBasicAuthCredential>(
+            "SELECT id, username, password_hash, consumer_id FROM basic_auth_credentials",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn add_basic_auth_credential(
+        &self,
+        username: &str,
+        password_hash: &str,
+        consumer_id: Option<i64>,
+    ) -> Result<DbBasicAuthCredential, sqlx::Error> {
+        sqlx::query_as::<_, DbBasicAuthCredential>(
+            "INSERT INTO basic_auth_credentials (username, password_hash, consumer_id) VALUES (?, ?, ?) RETURNING id, username, password_hash, consumer_id",
+        )
+        .bind(username)
+        .bind(password_hash)
+        .bind(consumer_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn delete_basic_auth_credential(&self, username: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM basic_auth_credentials WHERE username = ?")
+            .bind(username)
             .execute(&self.pool)
             .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn get_api_keys(&self) -> Result<Vec<DbApiKey>, sqlx::Error> {
-        sqlx::query_as::<_, DbApiKey>("SELECT id, name, key_hash FROM api_keys")
+    pub async fn get_hmac_credentials(&self) -> Result<Vec<DbHmacCredential>, sqlx::Error> {
+        sqlx::query_as::<_, DbHmacCredential>(
+            "SELECT id, key_id, secret_encrypted, consumer_id FROM hmac_credentials",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn add_hmac_credential(
+        &self,
+        key_id: &str,
+        secret_encrypted: &str,
+        consumer_id: Option<i64>,
+    ) -> Result<DbHmacCredential, sqlx::Error> {
+        sqlx::query_as::<_, DbHmacCredential>(
+            "INSERT INTO hmac_credentials (key_id, secret_encrypted, consumer_id) VALUES (?, ?, ?) RETURNING id, key_id, secret_encrypted, consumer_id",
+        )
+        .bind(key_id)
+        .bind(secret_encrypted)
+        .bind(consumer_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn delete_hmac_credential(&self, key_id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM hmac_credentials WHERE key_id = ?")
+            .bind(key_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_client_certificates(&self) -> Result<Vec<DbClientCertificate>, sqlx::Error> {
+        sqlx::query_as::<_, DbClientCertificate>(
+            "SELECT id, subject, consumer_id FROM client_certificates",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn add_client_certificate(
+        &self,
+        subject: &str,
+        consumer_id: Option<i64>,
+    ) -> Result<DbClientCertificate, sqlx::Error> {
+        sqlx::query_as::<_, DbClientCertificate>(
+            "INSERT INTO client_certificates (subject, consumer_id) VALUES (?, ?) RETURNING id, subject, consumer_id",
+        )
+        .bind(subject)
+        .bind(consumer_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn delete_client_certificate(&self, subject: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM client_certificates WHERE subject = ?")
+            .bind(subject)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_ip_rules(&self) -> Result<Vec<DbIpRule>, sqlx::Error> {
+        sqlx::query_as::<_, DbIpRule>("SELECT id, cidr, action, route_id FROM ip_rules")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn add_ip_rule(
+        &self,
+        cidr: &str,
+        action: IpAction,
+        route_id: Option<i64>,
+    ) -> Result<DbIpRule, sqlx::Error> {
+        sqlx::query_as::<_, DbIpRule>(
+            "INSERT INTO ip_rules (cidr, action, route_id) VALUES (?, ?, ?) RETURNING id, cidr, action, route_id",
+        )
+        .bind(cidr)
+        .bind(action)
+        .bind(route_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn delete_ip_rule(&self, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM ip_rules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_quota_usage(&self) -> Result<Vec<DbQuotaUsage>, sqlx::Error> {
+        sqlx::query_as::<_, DbQuotaUsage>("SELECT subject, period_start, used FROM quota_usage")
             .fetch_all(&self.pool)
             .await
     }
 
-    pub async fn add_api_key(&self, name: &str, key_hash: &str) -> Result<DbApiKey, sqlx::Error> {
-        sqlx::query_as::<_, DbApiKey>(
-            "INSERT INTO api_keys (name, key_hash) VALUES (?, ?) RETURNING id, name, key_hash",
+    pub async fn get_subject_quota_usage(
+        &self,
+        subject: &str,
+    ) -> Result<Option<DbQuotaUsage>, sqlx::Error> {
+        sqlx::query_as::<_, DbQuotaUsage>(
+            "SELECT subject, period_start, used FROM quota_usage WHERE subject = ?",
+        )
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn add_quota_usage(
+        &self,
+        subject: &str,
+        period_start: &str,
+        used: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+        INSERT INTO quota_usage (subject, period_start, used) VALUES (?, ?, ?)
+        ON CONFLICT(subject) DO UPDATE SET
+            used = CASE WHEN period_start = excluded.period_start
+                THEN used + excluded.used ELSE excluded.used END,
+            period_start = excluded.period_start
+        WHERE excluded.period_start >= period_start
+            "#,
+        )
+        .bind(subject)
+        .bind(period_start)
+        .bind(used)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn reset_quota_usage(&self, subject: &str) -> Result<bool, sqlx::Error> {
+        self.transaction(async |tx| tx.reset_quota_usage(subject).await)
+            .await
+    }
+
+    pub async fn get_global_rate_limit(&self) -> Result<Option<RateLimitPolicy>, sqlx::Error> {
+        let row: Option<(Json<RateLimitPolicy>,)> =
+            sqlx::query_as("SELECT value FROM settings WHERE key = 'rate_limit'")
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(row.map(|(policy,)| policy.0))
+    }
+
+    pub async fn set_global_rate_limit(&self, policy: &RateLimitPolicy) -> Result<(), sqlx::Error> {
+        self.transaction(async |tx| tx.set_global_rate_limit(policy).await)
+            .await
+    }
+
+    pub async fn delete_global_rate_limit(&self) -> Result<bool, sqlx::Error> {
+        self.transaction(async |tx| tx.delete_global_rate_limit().await)
+            .await
+    }
+
+    pub async fn get_audit_log(
+        &self,
+        filter: &AuditFilter,
+    ) -> Result<Vec<DbAuditEntry>, sqlx::Error> {
+        sqlx::query_as::<_, DbAuditEntry>(
+            r#"
+        SELECT id, actor, action, entity, entity_id, before, after, source_ip, created_at
+        FROM audit_log
+        WHERE (?1 IS NULL OR entity = ?1)
+          AND (?2 IS NULL OR created_at >= datetime(?2))
+          AND (?3 IS NULL OR created_at <= datetime(?3))
+        ORDER BY id DESC
+        LIMIT ?4
+            "#,
         )
+        .bind(&filter.entity)
+        .bind(&filter.from)
+        .bind(&filter.to)
+        .bind(filter.limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
+
+/// Changes made through a transaction are committed together, so a
+/// mutation and its audit entry are either both stored or both dropped.
+pub struct DatabaseTx {
+    tx: Transaction<'static, Sqlite>,
+}
+
+impl DatabaseTx {
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        self.tx.commit().await
+    }
+
+    pub async fn add_route(&mut self, route: &NewRoute) -> Result<DbRoute, sqlx::Error> {
+        sqlx::query_as::<_, DbRoute>(&format!(
+            "INSERT INTO routes(path, upstream, auth, forward_authorization, scopes, rate_limit, concurrency, load_shedding, request_headers, response_headers, cors) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING {}",
+            ROUTE_COLUMNS
+        ))
+        .bind(&route.path)
+        .bind(&route.upstream)
+        .bind(route.auth)
+        .bind(route.forward_authorization)
+        .bind(Json(&route.scopes))
+        .bind(route.policies.rate_limit.as_ref().map(Json))
+        .bind(route.policies.concurrency.as_ref().map(Json))
+        .bind(route.policies.load_shedding.as_ref().map(Json))
+        .bind(route.policies.request_headers.as_ref().map(Json))
+        .bind(route.policies.response_headers.as_ref().map(Json))
+        .bind(route.policies.cors.as_ref().map(Json))
+        .fetch_one(&mut *self.tx)
+        .await
+    }
+
+    pub async fn update_route_policies(
+        &mut self,
+        path: &str,
+        policies: &RoutePolicies,
+    ) -> Result<Option<DbRoute>, sqlx::Error> {
+        sqlx::query_as::<_, DbRoute>(&format!(
+            "UPDATE routes SET rate_limit = ?, concurrency = ?, load_shedding = ?, request_headers = ?, response_headers = ?, cors = ? WHERE path = ? RETURNING {}",
+            ROUTE_COLUMNS
+        ))
+        .bind(policies.rate_limit.as_ref().map(Json))
+        .bind(policies.concurrency.as_ref().map(Json))
+        .bind(policies.load_shedding.as_ref().map(Json))
+        .bind(policies.request_headers.as_ref().map(Json))
+        .bind(policies.response_headers.as_ref().map(Json))
+        .bind(policies.cors.as_ref().map(Json))
+        .bind(path)
+        .fetch_optional(&mut *self.tx)
+        .await
+    }
+
+    pub async fn update_route_rate_limit(
+        &mut self,
+        path: &str,
+        rate_limit: Option<&RateLimitPolicy>,
+    ) -> Result<Option<DbRoute>, sqlx::Error> {
+        sqlx::query_as::<_, DbRoute>(&format!(
+            "UPDATE routes SET rate_limit = ? WHERE path = ? RETURNING {}",
+            ROUTE_COLUMNS
+        ))
+        .bind(rate_limit.map(Json))
+        .bind(path)
+        .fetch_optional(&mut *self.tx)
+        .await
+    }
+
+    pub async fn delete_route(&mut self, path: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query("DELETE FROM ip_rules WHERE route_id = (SELECT id FROM routes WHERE path = ?)")
+            .bind(path)
+            .execute(&mut *self.tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM routes WHERE path = ?")
+            .bind(path)
+            .execute(&mut *self.tx)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn add_api_key(
+        &mut self,
+        name: &str,
+        key_hash: &str,
+        consumer_id: Option<i64>,
+        rate_limit: Option<&RateLimit>,
+        quota: Option<&Quota>,
+    ) -> Result<DbApiKey, sqlx::Error> {
+        sqlx::query_as::<_, DbApiKey>(&format!(
+            "INSERT INTO api_keys (name, key_hash, consumer_id, rate_limit, quota) VALUES (?, ?, ?, ?, ?) RETURNING {}",
+            API_KEY_COLUMNS
+        ))
         .bind(name)
         .bind(key_hash)
-        .fetch_one(&self.pool)
+        .bind(consumer_id)
+        .bind(rate_limit.map(Json))
+        .bind(quota.map(Json))
+        .fetch_one(&mut *self.tx)
         .await
     }
 
-    pub async fn delete_api_key(&self, name: &str) -> Result<bool, sqlx::Error> {
+    pub async fn delete_api_key(&mut self, name: &str) -> Result<bool, sqlx::Error> {
         let result = sqlx::query("DELETE FROM api_keys WHERE name = ?")
             .bind(name)
-            .execute(&self.pool)
+            .execute(&mut *self.tx)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_consumer_rate_limit(
+        &mut self,
+        name: &str,
+        rate_limit: Option<&RateLimit>,
+    ) -> Result<Option<DbConsumer>, sqlx::Error> {
+        sqlx::query_as::<_, DbConsumer>(&format!(
+            "UPDATE consumers SET rate_limit = ? WHERE name = ? RETURNING {}",
+            CONSUMER_COLUMNS
+        ))
+        .bind(rate_limit.map(Json))
+        .bind(name)
+        .fetch_optional(&mut *self.tx)
+        .await
+    }
+
+    pub async fn reset_quota_usage(&mut self, subject: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM quota_usage WHERE subject = ?")
+            .bind(subject)
+            .execute(&mut *self.tx)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_global_rate_limit(
+        &mut self,
+        policy: &RateLimitPolicy,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+        INSERT INTO settings (key, value) VALUES ('rate_limit', ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(Json(policy))
+        .execute(&mut *self.tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_global_rate_limit(&mut self) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM settings WHERE key = 'rate_limit'")
+            .execute(&mut *self.tx)
             .await?;
 
         Ok(result.rows_affected() > 0)
     }
+
+    pub async fn add_audit_entry(&mut self, entry: &NewAuditEntry) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO audit_log (actor, action, entity, entity_id, before, after, source_ip) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&entry.actor)
+        .bind(&entry.action)
+        .bind(&entry.entity)
+        .bind(&entry.entity_id)
+        .bind(entry.before.as_ref().map(Json))
+        .bind(entry.after.as_ref().map(Json))
+        .bind(&entry.source_ip)
+        .execute(&mut *self.tx)
+        .await?;
+
+        Ok(())
+    }
+}
+
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let columns: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+        .bind(table)
+        .fetch_all(pool)
+        .await?;
+
+    if !columns.iter().any(|(name,)| name == column) {
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,231 @@
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+// imports
+use crate::auth::identity::IdentityField;
+use crate::auth::signature::SIGNATURE_HEADERS;
+
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "proxy-authorization", "x-api-key"];
+
+pub fn is_credential_header(name: &str) -> bool {
+    CREDENTIAL_HEADERS
+        .iter()
+        .chain(SIGNATURE_HEADERS)
+        .any(|header| header.eq_ignore_ascii_case(name))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderTransforms {
+    #[serde(default)]
+    pub remove: Vec<String>,
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    #[serde(default)]
+    pub set: BTreeMap<String, String>,
+    #[serde(default)]
+    pub add: BTreeMap<String, String>,
+}
+
+impl HeaderTransforms {
+    pub fn validate(&self) -> Result<(), String> {
+        let names = self
+            .remove
+            .iter()
+            .chain(self.rename.keys())
+            .chain(self.rename.values())
+            .chain(self.set.keys())
+            .chain(self.add.keys());
+        for name in names {
+            if !is_header_name(name) {
+                return Err(format!("Invalid header name: {}", name));
+            }
+        }
+        // Renaming would carry a credential past the gateway's stripping.
+        if let Some(name) = self.rename.keys().find(|name| is_credential_header(name)) {
+            return Err(format!("Credential header cannot be renamed: {}", name));
+        }
+        for template in self.set.values().chain(self.add.values()) {
+            validate_template(template)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseHeaderRules {
+    #[serde(default)]
+    pub security_headers: bool,
+    #[serde(flatten)]
+    pub transforms: HeaderTransforms,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Variable<'a> {
+    ClientIp,
+    RequestId,
+    Method,
+    Path,
+    Route,
+    PathSegment(usize),
+    Header(&'a str),
+    Identity(IdentityField),
+}
+
+impl Variable<'_> {
+    fn is_credential(&self) -> bool {
+        matches!(self, Self::Header(name) if is_credential_header(name))
+    }
+}
+
+impl<'a> Variable<'a> {
+    fn parse(name: &'a str) -> Option<Self> {
+        if let Some(index) = name.strip_prefix("path.") {
+            let index: usize = index.parse().ok()?;
+            return (index > 0).then_some(Self::PathSegment(index));
+        }
+        if let Some(header) = name.strip_prefix("header.") {
+            return is_header_name(header).then_some(Self::Header(header));
+        }
+
+        match name {
+            "client_ip" => Some(Self::ClientIp),
+            "request_id" => Some(Self::RequestId),
+            "method" => Some(Self::Method),
+            "path" => Some(Self::Path),
+            "route" => Some(Self::Route),
+            _ => name.parse().ok().map(Self::Identity),
+        }
+    }
+}
+
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+fn parts(template: &str) -> impl Iterator<Item = Result<(&str, Option<&str>), String>> {
+    let mut rest = template;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let Some(start) = rest.find("${") else {
+            let literal = rest;
+            rest = "";
+            return Some(Ok((literal, None)));
+        };
+        let literal = &rest[..start];
+        let Some(end) = rest[start..].find('}') else {
+            rest = "";
+            return Some(Err(format!("Unclosed variable in {}", template)));
+        };
+        let name = &rest[start + 2..start + end];
+        rest = &rest[start + end + 1..];
+        Some(Ok((literal, Some(name))))
+    })
+}
+
+pub fn validate_template(template: &str) -> Result<(), String> {
+    for part in parts(template) {
+        let (_, Some(name)) = part? else {
+            continue;
+        };
+        match Variable::parse(name) {
+            None => return Err(format!("Unknown variable: {}", name)),
+            Some(variable) if variable.is_credential() => {
+                return Err(format!("Credential header cannot be used: {}", name));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Renders `template`, or returns `None` if any variable does not resolve.
+/// Credential headers never resolve.
+pub fn render<'a>(
+    template: &'a str,
+    resolve: impl Fn(Variable<'a>) -> Option<String>,
+) -> Option<String> {
+    let mut value = String::new();
+    for part in parts(template) {
+        let (literal, name) = part.ok()?;
+        value.push_str(literal);
+        if let Some(name) = name {
+            let variable = Variable::parse(name).filter(|variable| !variable.is_credential())?;
+            value.push_str(&resolve(variable)?);
+        }
+    }
+    Some(value)
+}
+
+pub fn path_segment<'a>(path: &'a str, route: &str, index: usize) -> Option<&'a str> {
+    path.strip_prefix(route)?
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .nth(index.checked_sub(1)?)
+}
+
+pub fn generate_request_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let resolve = |variable: Variable| match variable {
+            Variable::Identity(IdentityField::ConsumerName) => Some(String::from("partner")),
+            Variable::PathSegment(index) => {
+                path_segment("/users/42/orders", "/users", index).map(String::from)
+            }
+            Variable::Header(_) => Some(String::from("acme")),
+            _ => None,
+        };
+
+        assert_eq!(
+            render("${consumer.name}/${path.2}:${header.x-tenant}", resolve).as_deref(),
+            Some("partner/orders:acme")
+        );
+        assert_eq!(render("${consumer.name}/${path.3}", resolve), None);
+        assert_eq!(render("Bearer ${header.authorization}", resolve), None);
+    }
+
+    #[test]
+    fn test_validate_transforms() {
+        let mut transforms = HeaderTransforms::default();
+        transforms.set.insert(
+            String::from("X-Client"),
+            String::from("${client_ip} via ${route} for ${claims.sub}"),
+        );
+        assert!(transforms.validate().is_ok());
+
+        transforms
+            .add
+            .insert(String::from("X-Broken"), String::from("${client_ip"));
+        assert!(transforms.validate().is_err());
+
+        transforms.add.clear();
+        transforms
+            .rename
+            .insert(String::from("X-Old"), String::from("X New"));
+        assert!(transforms.validate().is_err());
+
+        transforms.rename.clear();
+        transforms
+            .rename
+            .insert(String::from("Authorization"), String::from("X-Token"));
+        assert!(transforms.validate().is_err());
+
+        assert!(validate_template("${path.0}").is_err());
+        assert!(validate_template("${unknown}").is_err());
+        assert!(validate_template("${header.X-Api-Key}").is_err());
+    }
+}